@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use justone::{JustOne, NoopObserver};
+
+const FILE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Two identical large files, so both go through the full hash
+fn setup() -> PathBuf {
+    let dir = std::env::temp_dir().join("justone-bench-read-buffer");
+    fs::create_dir_all(&dir).unwrap();
+    let content: Vec<u8> = (0..FILE_SIZE).map(|i| (i * 31 % 251) as u8).collect();
+    fs::write(dir.join("a"), &content).unwrap();
+    fs::write(dir.join("b"), &content).unwrap();
+    dir
+}
+
+fn read_buffer_size(c: &mut Criterion) {
+    let dir = setup();
+    let mut group = c.benchmark_group("full_hash_read_buffer");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(2 * FILE_SIZE as u64));
+    for &size in &[8 * 1024, 128 * 1024, 1024 * 1024] {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| {
+                let mut jo = JustOne::new()
+                    .with_read_buffer_size(size)
+                    .with_progress_observer(NoopObserver);
+                jo.update(&dir).unwrap();
+                assert_eq!(jo.duplicates().unwrap().len(), 1);
+            })
+        });
+    }
+    group.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(benches, read_buffer_size);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use justone::{JustOne, NoopObserver};
+
+const PAIRS: usize = 200;
+
+/// Pairs of same-size files of `size` bytes, half of them identical and half differing
+/// from their first byte
+fn setup(size: usize) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("justone-bench-small-hash-{}", size));
+    fs::create_dir_all(&dir).unwrap();
+    for i in 0..PAIRS {
+        let mut content: Vec<u8> = (0..size).map(|j| (i + j * 31 % 251) as u8).collect();
+        // a distinct size for each pair, so only the pairs are candidates
+        content.resize(size + i, 0);
+        fs::write(dir.join(format!("{}-a", i)), &content).unwrap();
+        if i % 2 == 1 {
+            content[0] ^= 1;
+        }
+        fs::write(dir.join(format!("{}-b", i)), &content).unwrap();
+    }
+    dir
+}
+
+fn skip_small_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("skip_small_hash");
+    group.sample_size(20);
+    for &size in &[1024, 2 * 1024, 4 * 1024, 8 * 1024, 16 * 1024] {
+        let dir = setup(size);
+        for &skip in &[false, true] {
+            let id = BenchmarkId::new(if skip { "skip" } else { "two_passes" }, size);
+            group.bench_with_input(id, &skip, |b, &skip| {
+                b.iter(|| {
+                    let mut jo = JustOne::new()
+                        .with_skip_small_hash(skip)
+                        .with_progress_observer(NoopObserver);
+                    jo.update(&dir).unwrap();
+                    assert_eq!(jo.duplicates().unwrap().len(), PAIRS / 2);
+                })
+            });
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    group.finish();
+}
+
+criterion_group!(benches, skip_small_hash);
+criterion_main!(benches);
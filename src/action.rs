@@ -0,0 +1,249 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    file_cmp, get_device, split_identical, FileIndex, JustOne, JustOneError, Result, StrictLevel,
+};
+
+/// The kept file of a group and the files to act on
+type GroupPlan = (FileIndex, Vec<FileIndex>);
+
+/// Which file of a duplicate group is kept by an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepStrategy {
+    /// The least recently modified file
+    Oldest,
+    /// The most recently modified file
+    Newest,
+    /// The file with the fewest path components, then the shortest path
+    ShortestPath,
+    /// The file whose path comes first in lexicographic order
+    FirstAlphabetical,
+    /// The file indexed first, i.e. from the first scanned folder
+    FirstFound,
+}
+
+/// What an action did
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ActionSummary {
+    /// The files deleted, or replaced by a hardlink to the kept file
+    pub files: Vec<PathBuf>,
+    /// The size of those files
+    pub bytes_freed: u64,
+}
+
+/// What `remove_duplicates` would do with a duplicate group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovalPlan {
+    /// The file kept
+    pub kept: PathBuf,
+    /// The other files of the group, which would be deleted
+    pub remove: Vec<PathBuf>,
+}
+
+impl JustOne {
+    /// Return what `remove_duplicates` would keep and delete for each duplicate group,
+    /// without touching the filesystem.
+    ///
+    /// It fails the same way if one of the files to delete is under a read-only root.
+    pub fn plan_removals(&self, keep: KeepStrategy) -> Result<Vec<RemovalPlan>> {
+        let (plan, _) = self.plan(keep, false)?;
+        Ok(plan
+            .into_iter()
+            .map(|(kept, others)| RemovalPlan {
+                kept: self.get_file_path_by_index(kept).to_owned(),
+                remove: others
+                    .into_iter()
+                    .map(|file_index| self.get_file_path_by_index(file_index).to_owned())
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Check that the files of a group are still identical right now, e.g. right before
+    /// acting on a group reported by an earlier scan, since the files may have changed since
+    /// they were hashed. The files are compared with the first one byte by byte (or by stat
+    /// first if `shallow`), without using any hash.
+    ///
+    /// Return the first pair of files found different, or `None` if they are all identical.
+    pub fn verify_group<'a>(
+        &self,
+        paths: &[&'a Path],
+        shallow: bool,
+    ) -> Result<Option<(&'a Path, &'a Path)>> {
+        if let Some((&first, others)) = paths.split_first() {
+            for &path in others {
+                if !file_cmp(first, path, shallow)? {
+                    return Ok(Some((first, path)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Keep one file of each duplicate group and delete the others.
+    ///
+    /// Only the files found identical byte by byte are deleted, whatever the strict level.
+    /// Nothing is deleted if one of the files to delete is under a read-only root. Symlink
+    /// groups are left alone, and the index is not updated.
+    pub fn remove_duplicates(&mut self, keep: KeepStrategy) -> Result<ActionSummary> {
+        self.apply(keep, false, |_, path| fs::remove_file(path))
+    }
+
+    /// Keep one file of each duplicate group and replace the others with hardlinks to it, so
+    /// they share its storage. All the paths still exist afterward. Like `remove_duplicates`,
+    /// only the files found identical byte by byte are replaced.
+    ///
+    /// Nothing is modified if one of the files to replace is under a read-only root. A group
+    /// whose files span several filesystems can't share one file, it is left as it is and
+    /// its files are listed by `ignored` with `io::ErrorKind::CrossesDevices`, even with
+    /// `ErrorPolicy::FailFast`.
+    pub fn link_duplicates(&mut self, keep: KeepStrategy) -> Result<ActionSummary> {
+        self.apply(keep, true, replace_with_hardlink)
+    }
+
+    /// Plan the groups, then run `action(kept, path)` on every other file of each group
+    fn apply(
+        &mut self,
+        keep: KeepStrategy,
+        same_device: bool,
+        action: impl Fn(&Path, &Path) -> io::Result<()>,
+    ) -> Result<ActionSummary> {
+        let (plan, cross_device) = self.plan(keep, same_device)?;
+        let mut summary = ActionSummary::default();
+        let mut ignored: Vec<(PathBuf, JustOneError)> = cross_device
+            .into_iter()
+            .map(|file_index| {
+                let path = self.get_file_path_by_index(file_index).to_owned();
+                let error = io::Error::from(io::ErrorKind::CrossesDevices);
+                (path.clone(), io_error!(error, path))
+            })
+            .collect();
+        for (kept, others) in plan {
+            let kept_path = self.get_file_path_by_index(kept);
+            for file_index in others {
+                let file_info = self.get_file_info(file_index);
+                match action(kept_path, &file_info.path) {
+                    Ok(()) => {
+                        summary.files.push(file_info.path.clone());
+                        summary.bytes_freed += file_info.size as u64;
+                    }
+                    // a file on another filesystem than the kept one is left intact
+                    Err(e) if self.skips_errors() || e.kind() == io::ErrorKind::CrossesDevices => {
+                        ignored.push((file_info.path.clone(), io_error!(e, file_info.path)))
+                    }
+                    Err(e) => return Err(io_error!(e, file_info.path)),
+                }
+            }
+        }
+        for (path, error) in ignored {
+            self.add_ignored(path, error);
+        }
+        Ok(summary)
+    }
+
+    /// Return the kept file and the files to act on for each duplicate group, refusing any
+    /// group where a file to act on is under a read-only root. With `same_device`, the
+    /// groups whose files span several devices are left out, and their files returned
+    /// apart.
+    ///
+    /// A hash collision must not cost a file, so the groups are split by a byte comparison
+    /// first, unless the strict level already compared the bytes. With
+    /// `with_full_hash_limit`, the groups are even only candidates sharing the start of
+    /// their content.
+    pub(crate) fn plan(
+        &self,
+        keep: KeepStrategy,
+        same_device: bool,
+    ) -> Result<(Vec<GroupPlan>, Vec<FileIndex>)> {
+        let verified = matches!(
+            self.strict_level,
+            StrictLevel::CommonVerified | StrictLevel::ByteByByte
+        );
+        let mut plan = Vec::new();
+        let mut cross_device = Vec::new();
+        for group in self.duplicate_file_groups() {
+            let group = group?;
+            let groups = if verified {
+                vec![group]
+            } else {
+                split_identical(group, false)?
+            };
+            for group in groups {
+                if same_device && spans_devices(&group) {
+                    cross_device.extend(
+                        group
+                            .iter()
+                            .map(|path| self.lookup_file_index(path).unwrap()),
+                    );
+                    continue;
+                }
+                plan.push(self.plan_group(keep, &group)?);
+            }
+        }
+        Ok((plan, cross_device))
+    }
+
+    /// Return the kept file and the files to act on in a group of identical files
+    fn plan_group(&self, keep: KeepStrategy, group: &[&Path]) -> Result<GroupPlan> {
+        let mut group: Vec<FileIndex> = group
+            .iter()
+            .map(|path| self.lookup_file_index(path).unwrap())
+            .collect();
+        let kept = self.pick(keep, &group);
+        group.retain(|&file_index| file_index != kept);
+        if let Some(&protected) = group
+            .iter()
+            .find(|&&file_index| self.is_readonly(self.get_file_path_by_index(file_index)))
+        {
+            let path = self.get_file_path_by_index(protected).to_owned();
+            return Err(JustOneError::ReadOnlyError(path));
+        }
+        Ok((kept, group))
+    }
+
+    /// Return the file to keep among a (non-empty) group
+    fn pick(&self, keep: KeepStrategy, group: &[FileIndex]) -> FileIndex {
+        let files = group
+            .iter()
+            .map(|&file_index| (file_index, self.get_file_info(file_index)));
+        let kept = match keep {
+            KeepStrategy::Oldest => {
+                files.min_by_key(|(i, f)| (f.modified.is_none(), f.modified, *i))
+            }
+            KeepStrategy::Newest => files.max_by_key(|(i, f)| (f.modified, std::cmp::Reverse(*i))),
+            KeepStrategy::ShortestPath => files
+                .min_by_key(|(i, f)| (f.path.components().count(), f.path.as_os_str().len(), *i)),
+            KeepStrategy::FirstAlphabetical => files.min_by_key(|(_, f)| &f.path),
+            KeepStrategy::FirstFound => files.min_by_key(|(i, _)| *i),
+        };
+        kept.unwrap().0
+    }
+}
+
+/// Replace `path` with a hardlink to `kept`, through a temporary link renamed over `path`
+/// so `path` never goes missing.
+fn replace_with_hardlink(kept: &Path, path: &Path) -> io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".justone-link");
+    let temp_path = path.with_file_name(temp_name);
+    fs::hard_link(kept, &temp_path)?;
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+/// Return true if the files of `group` are on several devices, a file whose device can't be
+/// read doesn't count
+fn spans_devices(group: &[&Path]) -> bool {
+    let mut devices = group.iter().filter_map(|path| {
+        fs::metadata(path)
+            .ok()
+            .and_then(|metadata| get_device(&metadata))
+    });
+    match devices.next() {
+        Some(first) => devices.any(|device| device != first),
+        None => false,
+    }
+}
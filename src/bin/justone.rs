@@ -1,17 +1,22 @@
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
 use clap::{App, Arg};
-use justone::{default_hasher_creator, JustOne, StrictLevel};
+use justone::{
+    FileSize, HashAlgorithm, JustOne, JustOneError, KeepStrategy, StrictLevel, SymlinkPolicy,
+};
 
-const APP_NAME: &'static str = env!("CARGO_PKG_NAME");
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
-const AUTHORS: &'static str = env!("CARGO_PKG_AUTHORS");
-const BIN_NAME: &'static str = env!("CARGO_BIN_NAME");
-const DESCRIPTION: &'static str = env!("CARGO_PKG_DESCRIPTION");
+const APP_NAME: &str = env!("CARGO_PKG_NAME");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
+const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+const DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 
 macro_rules! fatal {
     ($($tt:tt)*) => {{
@@ -44,6 +49,12 @@ fn main() {
             .long_help("[0][default] Based on hash comparison.\n\
                 [1][-s] Shallow comparison based on file stat, and byte comparison when inconsistent, to prevent hash collision.\n\
                 [2][-ss] Strictly compare byte by byte to prevent file stat and hash collision.\n"))
+        .arg(Arg::with_name("verify")
+            .long("verify")
+            .help("Compare the files byte by byte after the hash comparison, to rule out hash collisions")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
         .arg(Arg::with_name("ignore-error")
             .short("i")
             .long("ignore-error")
@@ -51,6 +62,31 @@ fn main() {
             .takes_value(false)
             .required(false)
             .multiple(false))
+        .arg(Arg::with_name("show-skipped")
+            .long("show-skipped")
+            .help("List the files skipped because of an error after the results, with --ignore-error")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("show-root")
+            .long("show-root")
+            .help("Follow each path of the text results with the scanned folder it was found in, e.g. to tell the backup copies from the source ones")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("summary")
+            .long("summary")
+            .help("Print the number of duplicate groups, redundant files and reclaimable space after the results")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .help("Hide the progress bars and the messages which are not errors, the log level can be set with RUST_LOG")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
         .arg(Arg::with_name("time")
             .short("t")
             .long("time")
@@ -58,6 +94,190 @@ fn main() {
             .takes_value(false)
             .required(false)
             .multiple(false))
+        .arg(Arg::with_name("ignore-empty")
+            .long("ignore-empty")
+            .help("Skip all empty files")
+            .takes_value(false)
+            .required(false)
+            .multiple(false)
+            .conflicts_with("include-zero-size-distinctly"))
+        .arg(Arg::with_name("min-size")
+            .long("min-size")
+            .value_name("SIZE")
+            .help("Skip the files smaller than SIZE bytes, with an optional K, M or G suffix (e.g. 1K)")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("max-size")
+            .long("max-size")
+            .value_name("SIZE")
+            .help("Skip the files larger than SIZE bytes, with an optional K, M or G suffix (e.g. 10G), listed by --show-skipped")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("include-zero-size-distinctly")
+            .long("include-zero-size-distinctly")
+            .help("Report all empty files as one separate group instead of mixing them into the duplicates")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("one-file-system")
+            .long("one-file-system")
+            .help("Don't descend into folders on other filesystems, such as mount points")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("same-name")
+            .long("same-name")
+            .help("Only report files with the same file name as duplicates")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("follow-links")
+            .long("follow-links")
+            .help("Traverse the symlinks to folders and compare the symlinks to files by their target content")
+            .takes_value(false)
+            .required(false)
+            .multiple(false)
+            .conflicts_with("no-symlinks"))
+        .arg(Arg::with_name("follow-internal-links")
+            .long("follow-internal-links")
+            .help("Same as --follow-links, but only for the symlinks pointing inside the scanned folders, skipping the others")
+            .takes_value(false)
+            .required(false)
+            .multiple(false)
+            .conflicts_with_all(&["follow-links", "no-symlinks"]))
+        .arg(Arg::with_name("no-symlinks")
+            .long("no-symlinks")
+            .help("Skip all symlinks")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("algorithm")
+            .short("a")
+            .long("algorithm")
+            .help("Hash algorithm for the full file content")
+            .takes_value(true)
+            .possible_values(&["xxhash64", "sha256", "blake3"])
+            .default_value("xxhash64")
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("small-hash-bytes")
+            .long("small-hash-bytes")
+            .value_name("BYTES")
+            .help("How many bytes from the start of the files the first quick comparison reads [default: 1024]")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("hash-limit")
+            .long("hash-limit")
+            .value_name("BYTES")
+            .help("Only hash the first BYTES bytes of the files, reporting candidates which still need to be confirmed")
+            .takes_value(true)
+            .required(false)
+            .multiple(false)
+            .conflicts_with("delete"))
+        .arg(Arg::with_name("normalize-text")
+            .long("normalize-text")
+            .help("Compare text files ignoring their line endings (CRLF or LF) and trailing blanks")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("max-read-rate")
+            .long("max-read-rate")
+            .value_name("RATE")
+            .help("Read at most RATE bytes per second while hashing, with an optional K, M or G suffix (e.g. 50M), to spare a shared disk")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .value_name("SEED")
+            .help("Seed of the xxhash hasher, a cache saved with another seed is discarded")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("ext")
+            .long("ext")
+            .value_name("EXT")
+            .help("Only compare the files with this extension, case-insensitive (repeatable or comma separated)")
+            .takes_value(true)
+            .use_delimiter(true)
+            .required(false)
+            .multiple(true))
+        .arg(Arg::with_name("exclude")
+            .short("e")
+            .long("exclude")
+            .value_name("GLOB")
+            .help("Skip the files and folders matching the glob, relative to the scanned folder (repeatable)")
+            .takes_value(true)
+            .number_of_values(1)
+            .required(false)
+            .multiple(true))
+        .arg(Arg::with_name("max-depth")
+            .long("max-depth")
+            .value_name("DEPTH")
+            .help("Descend at most DEPTH levels below the folder, 0 being the folder itself")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("min-depth")
+            .long("min-depth")
+            .value_name("DEPTH")
+            .help("Skip the entries less than DEPTH levels below the folder")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("cache")
+            .short("c")
+            .long("cache")
+            .value_name("FILE")
+            .help("Reuse the hashes saved in the cache file for unchanged files, and update it")
+            .takes_value(true)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("keep")
+            .short("k")
+            .long("keep")
+            .help("Which file of each duplicate group is kept by --delete")
+            .takes_value(true)
+            .possible_values(&["first", "oldest", "newest", "shortest", "alphabetical"])
+            .default_value("first")
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("delete")
+            .long("delete")
+            .help("Delete all the duplicates but the kept file of each group, once checked identical byte by byte")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("dry-run")
+            .long("dry-run")
+            .help("Print the files --delete would keep and remove, without touching any file")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("uniques")
+            .long("uniques")
+            .help("Print the files which have no duplicate instead of the duplicates, one per line")
+            .takes_value(false)
+            .required(false)
+            .multiple(false)
+            .conflicts_with_all(&["delete", "dry-run"]))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .help("Output format of the results, json includes the sizes, statistics and skipped files, hash shows the hash and size of each group, html writes a page to share with collapsible groups, csv writes a row per file with its group, size and hash")
+            .takes_value(true)
+            .possible_values(&["text", "json", "hash", "html", "csv"])
+            .default_value("text")
+            .required(false)
+            .multiple(false))
+        .arg(Arg::with_name("print0")
+            .long("print0")
+            .help("Print each path followed by a NUL byte and the groups separated by an extra NUL byte, for xargs -0")
+            .takes_value(false)
+            .required(false)
+            .multiple(false))
         .arg(Arg::with_name("output")
             .short("o")
             .long("output")
@@ -71,13 +291,96 @@ fn main() {
     let strict_level = matches.occurrences_of("strict");
     let ignore_error = matches.is_present("ignore-error");
     let time_it = matches.is_present("time");
+    let quiet = matches.is_present("quiet");
+    let show_skipped = matches.is_present("show-skipped");
+    let show_root = matches.is_present("show-root");
+    let summary = matches.is_present("summary");
+    let uniques = matches.is_present("uniques");
+    let delete = matches.is_present("delete");
+    let dry_run = matches.is_present("dry-run");
+    let keep = match matches.value_of("keep") {
+        Some("oldest") => KeepStrategy::Oldest,
+        Some("newest") => KeepStrategy::Newest,
+        Some("shortest") => KeepStrategy::ShortestPath,
+        Some("alphabetical") => KeepStrategy::FirstAlphabetical,
+        _ => KeepStrategy::FirstFound,
+    };
+    let format = match matches.value_of("format") {
+        _ if matches.is_present("print0") => Format::Print0,
+        Some("json") => Format::Json,
+        Some("hash") => Format::Hash,
+        Some("html") => Format::Html,
+        Some("csv") => Format::Csv,
+        _ => Format::Text,
+    };
     let output = matches.value_of("output");
+    let empty_distinctly = matches.is_present("include-zero-size-distinctly");
+    let cache = matches.value_of("cache").map(Path::new);
+    let excludes: Vec<String> = matches
+        .values_of("exclude")
+        .map(|values| values.map(String::from).collect())
+        .unwrap_or_default();
+    let max_depth = matches
+        .value_of("max-depth")
+        .map(|depth| parse_depth("max-depth", depth));
+    let min_depth = matches
+        .value_of("min-depth")
+        .map(|depth| parse_depth("min-depth", depth));
+    let extensions: Option<Vec<String>> = matches
+        .values_of("ext")
+        .map(|values| values.map(String::from).collect());
+    let small_hash_size =
+        matches
+            .value_of("small-hash-bytes")
+            .map(|size| match size.parse::<usize>() {
+                Ok(size) => size,
+                Err(_) => fatal!(
+                    "--small-hash-bytes needs a non-negative integer, got {}",
+                    size
+                ),
+            });
+    let full_hash_limit = matches
+        .value_of("hash-limit")
+        .map(|limit| match limit.parse::<u64>() {
+            Ok(limit) => limit,
+            Err(_) => fatal!("--hash-limit needs a non-negative integer, got {}", limit),
+        });
+    let max_read_rate = matches
+        .value_of("max-read-rate")
+        .map(|rate| match parse_size(rate) {
+            Some(rate) => rate,
+            None => fatal!("--max-read-rate needs a number of bytes like 50M, got {}", rate),
+        });
+    let min_size = matches
+        .value_of("min-size")
+        .map(|size| match parse_size(size) {
+            Some(size) => size as FileSize,
+            None => fatal!("--min-size needs a number of bytes like 1K, got {}", size),
+        });
+    let max_size = matches
+        .value_of("max-size")
+        .map(|size| match parse_size(size) {
+            Some(size) => size as FileSize,
+            None => fatal!("--max-size needs a number of bytes like 10G, got {}", size),
+        });
+    let seed = matches
+        .value_of("seed")
+        .map(|seed| match seed.parse::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => fatal!("--seed needs a non-negative integer, got {}", seed),
+        });
+    let algorithm = match matches.value_of("algorithm") {
+        Some("sha256") => HashAlgorithm::Sha256,
+        Some("blake3") => HashAlgorithm::Blake3,
+        _ => HashAlgorithm::XxHash64,
+    };
 
     let strict_level = match strict_level {
+        0 if matches.is_present("verify") => StrictLevel::CommonVerified,
         0 => StrictLevel::Common,
         1 => StrictLevel::Shallow,
         2 => StrictLevel::ByteByByte,
-        x @ _ => fatal!(
+        x => fatal!(
             "{} is not a valid level for file comparison strict level. (need -s, -ss or unset)",
             x
         ),
@@ -92,43 +395,476 @@ fn main() {
         Box::new(io::stdout())
     };
 
-    if let Err(e) = print_duplicates(folders, output, strict_level, ignore_error, time_it) {
+    // Ctrl-C stops the scan, the duplicates found so far are still printed
+    let cancel_token = Arc::new(AtomicBool::new(false));
+    {
+        let cancel_token = Arc::clone(&cancel_token);
+        let _ = ctrlc::set_handler(move || cancel_token.store(true, Ordering::Relaxed));
+    }
+
+    let symlink_policy = if matches.is_present("no-symlinks") {
+        SymlinkPolicy::Skip
+    } else if matches.is_present("follow-links") {
+        SymlinkPolicy::Follow
+    } else if matches.is_present("follow-internal-links") {
+        SymlinkPolicy::FollowInternal
+    } else {
+        SymlinkPolicy::Compare
+    };
+
+    let default_level = if quiet { "error" } else { "warn" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level))
+        .init();
+
+    let mut builder = JustOne::builder()
+        .cancel_token(cancel_token)
+        .strict_level(strict_level)
+        .ignore_error(ignore_error)
+        .ignore_empty(matches.is_present("ignore-empty"))
+        .same_filesystem(matches.is_present("one-file-system"))
+        .require_same_name(matches.is_present("same-name"))
+        .normalize_text(matches.is_present("normalize-text"))
+        .symlink_policy(symlink_policy)
+        .show_progress(!quiet)
+        .algorithm(algorithm);
+    if let Some(extensions) = extensions {
+        builder = builder.extensions(extensions);
+    }
+    if let Some(size) = small_hash_size {
+        builder = builder.small_hash_size(size);
+    }
+    if let Some(size) = min_size {
+        builder = builder.min_size(size);
+    }
+    if let Some(size) = max_size {
+        builder = builder.max_size(size);
+    }
+    if let Some(limit) = full_hash_limit {
+        builder = builder.full_hash_limit(limit);
+    }
+    if let Some(rate) = max_read_rate {
+        builder = builder.io_rate_limit(rate);
+    }
+    if let Some(seed) = seed {
+        builder = builder.seed(seed);
+    }
+    if let Some(depth) = max_depth {
+        builder = builder.max_depth(depth);
+    }
+    if let Some(depth) = min_depth {
+        builder = builder.min_depth(depth);
+    }
+    if !excludes.is_empty() {
+        builder = match builder.excludes(excludes) {
+            Ok(builder) => builder,
+            Err(e) => fatal!("Invalid exclude pattern: {}", e),
+        };
+    }
+    let jo = builder.build();
+
+    let options = Options {
+        time_it,
+        quiet,
+        show_skipped,
+        show_root,
+        summary,
+        uniques,
+        empty_distinctly,
+        keep,
+        delete,
+        dry_run,
+        format,
+        cache,
+    };
+
+    if let Err(e) = print_duplicates(jo, folders, output, &options) {
         fatal!("Error: {}", e);
     };
 }
 
+fn parse_depth(name: &str, depth: &str) -> usize {
+    match depth.parse() {
+        Ok(depth) => depth,
+        Err(_) => fatal!("--{} needs a non-negative integer, got {}", name, depth),
+    }
+}
+
+/// Parse a number of bytes with an optional binary suffix, e.g. `50M` for 50 MiB
+fn parse_size(size: &str) -> Option<u64> {
+    let (digits, multiplier) = match size.char_indices().last()? {
+        (i, 'k') | (i, 'K') => (&size[..i], 1 << 10),
+        (i, 'm') | (i, 'M') => (&size[..i], 1 << 20),
+        (i, 'g') | (i, 'G') => (&size[..i], 1 << 30),
+        _ => (size, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Options of the command line which are not part of the `JustOne` configuration
+struct Options<'a> {
+    time_it: bool,
+    quiet: bool,
+    show_skipped: bool,
+    show_root: bool,
+    summary: bool,
+    uniques: bool,
+    empty_distinctly: bool,
+    keep: KeepStrategy,
+    delete: bool,
+    dry_run: bool,
+    format: Format,
+    cache: Option<&'a Path>,
+}
+
+/// How the results are printed
+#[derive(PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+    Hash,
+    /// A self-contained page
+    Html,
+    /// A row per file of the groups, for spreadsheets
+    Csv,
+    /// NUL-terminated paths
+    Print0,
+}
+
 fn print_duplicates(
-    folders: Vec<impl AsRef<Path>>,
+    mut jo: JustOne,
+    folders: Vec<impl AsRef<Path> + Sync>,
     mut output: Box<dyn Write>,
-    strict_level: StrictLevel,
-    ignore_error: bool,
-    time_it: bool,
+    options: &Options,
 ) -> Result<(), Box<dyn Error>> {
-    let mut jo = JustOne::with_full_config(default_hasher_creator(), strict_level, ignore_error);
+    if let Some(cache) = options.cache {
+        if cache.exists() {
+            jo.load_cache(cache)?;
+        }
+    }
 
     let start = Instant::now();
 
-    for folder in folders {
-        jo.update(folder)?;
+    let cancelled = match jo.update_all(&folders) {
+        Ok(_) => false,
+        Err(JustOneError::Cancelled) => true,
+        Err(e) => return Err(e.into()),
+    };
+
+    if let Some(cache) = options.cache {
+        jo.save_cache(cache)?;
     }
 
-    let dups = jo.duplicates()?;
+    if options.delete || options.dry_run {
+        // a partial index must never decide what gets deleted
+        if cancelled {
+            return Err(JustOneError::Cancelled.into());
+        }
+        print_removals(&mut jo, &mut output, options)?;
+        if options.time_it {
+            eprintln!("Time Waste: {:?}s", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    if cancelled && !options.quiet {
+        eprintln!("Cancelled, the results are partial.");
+    }
+
+    if options.uniques {
+        let mut uniques = jo.uniques()?;
+        uniques.sort_unstable();
+        for path in uniques {
+            if options.format == Format::Print0 {
+                write_path0(&mut output, path)?;
+            } else {
+                writeln!(&mut output, "{}", path.display())?;
+            }
+        }
+        if options.time_it {
+            eprintln!("Time Waste: {:?}s", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    if options.format == Format::Json {
+        serde_json::to_writer_pretty(&mut output, &jo.into_report()?)?;
+        writeln!(&mut output)?;
+        if options.time_it {
+            eprintln!("Time Waste: {:?}s", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    if options.format == Format::Csv {
+        write_csv(&jo, &mut output)?;
+        if options.time_it {
+            eprintln!("Time Waste: {:?}s", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    if options.format == Format::Html {
+        write_html(&jo, &mut output)?;
+        if options.time_it {
+            eprintln!("Time Waste: {:?}s", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    if options.format == Format::Hash {
+        let mut groups = jo.duplicates_with_hash()?;
+        for group in &mut groups {
+            group.paths.sort_unstable();
+        }
+        groups.sort_unstable_by(|a, b| a.paths.cmp(&b.paths));
+        for (i, group) in groups.iter().enumerate() {
+            if i != 0 {
+                writeln!(&mut output)?;
+            }
+            writeln!(&mut output, "{}", group)?;
+            for path in &group.paths {
+                writeln!(&mut output, " - {}", path.display())?;
+            }
+        }
+        if options.time_it {
+            eprintln!("Time Waste: {:?}s", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    let mut dups = jo.duplicates_sorted()?;
+
+    if options.format == Format::Print0 {
+        for (i, dup) in dups.iter().enumerate() {
+            if i != 0 {
+                output.write_all(b"\0")?;
+            }
+            for path in dup {
+                write_path0(&mut output, path)?;
+            }
+        }
+        if options.time_it {
+            eprintln!("Time Waste: {:?}s", start.elapsed());
+        }
+        return Ok(());
+    }
 
     let time_waste = start.elapsed();
 
+    let empty_files = if options.empty_distinctly {
+        let empty_files = jo.empty_files();
+        let empty_set: HashSet<&Path> = empty_files.iter().copied().collect();
+        dups.retain(|dup| !dup.iter().all(|path| empty_set.contains(path)));
+        empty_files
+    } else {
+        Vec::new()
+    };
+
     for (i, dup) in dups.iter().enumerate() {
         if i != 0 {
-            writeln!(&mut output, "")?;
+            writeln!(&mut output)?;
+        }
+        if jo.reports_candidates() {
+            writeln!(&mut output, "[{}] Candidate found, to be confirmed:", i + 1)?;
+        } else {
+            writeln!(&mut output, "[{}] Duplicate found:", i + 1)?;
         }
-        writeln!(&mut output, "[{}] Duplicate found:", i + 1)?;
         for path in dup {
+            match jo.root_of(path) {
+                Some(root) if options.show_root => {
+                    writeln!(&mut output, " - {} [{}]", path.display(), root.display())?
+                }
+                _ => writeln!(&mut output, " - {}", path.display())?,
+            }
+        }
+    }
+
+    if !empty_files.is_empty() {
+        if !dups.is_empty() {
+            writeln!(&mut output)?;
+        }
+        writeln!(&mut output, "Empty files (0 bytes):")?;
+        for path in &empty_files {
             writeln!(&mut output, " - {}", path.display())?;
         }
     }
 
-    if time_it {
-        println!("Time Waste: {:?}s", time_waste);
+    let skipped_shown =
+        options.show_skipped && (!jo.ignored().is_empty() || !jo.too_large().is_empty());
+    if skipped_shown {
+        if !dups.is_empty() || !empty_files.is_empty() {
+            writeln!(&mut output)?;
+        }
+        writeln!(&mut output, "Skipped files:")?;
+        for ignored in jo.ignored() {
+            writeln!(
+                &mut output,
+                " - {} ({:?})",
+                ignored.path.display(),
+                ignored.kind
+            )?;
+        }
+        for path in jo.too_large() {
+            writeln!(&mut output, " - {} (too large)", path.display())?;
+        }
+    }
+
+    if options.summary {
+        if !dups.is_empty() || !empty_files.is_empty() || skipped_shown {
+            writeln!(&mut output)?;
+        }
+        writeln!(&mut output, "{}", jo.summary()?)?;
+    }
+
+    if options.time_it {
+        eprintln!("Time Waste: {:?}s", time_waste);
+    }
+
+    Ok(())
+}
+
+const HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Duplicate files</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+details { border: 1px solid #ccc; border-radius: 4px; margin: 0.5em 0; padding: 0.5em; }
+summary { cursor: pointer; font-weight: bold; }
+ul { list-style: none; padding-left: 0; }
+li { display: flex; align-items: center; margin: 0.3em 0; }
+.thumb { width: 48px; height: 48px; margin-right: 0.8em; background: #eee; color: #888;
+  display: flex; align-items: center; justify-content: center; font-size: 0.7em; }
+code { word-break: break-all; }
+</style>
+</head>
+<body>
+"#;
+
+/// Write a `group_id,path,size_bytes,hash` row per file of the duplicate groups, the groups
+/// numbered from 1 in the order of `--format hash`
+fn write_csv(jo: &JustOne, output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let mut groups = jo.duplicates_with_hash()?;
+    for group in &mut groups {
+        group.paths.sort_unstable();
     }
+    groups.sort_unstable_by(|a, b| a.paths.cmp(&b.paths));
 
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(["group_id", "path", "size_bytes", "hash"])?;
+    for (i, group) in groups.iter().enumerate() {
+        let group_id = (i + 1).to_string();
+        let size = group.size.to_string();
+        let hash = format!("{}:{}", group.algorithm, group.hash);
+        for path in &group.paths {
+            let path = path.to_string_lossy();
+            writer.write_record([&group_id, &*path, &size, &hash])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the duplicate groups as a self-contained HTML page, the largest groups first
+fn write_html(jo: &JustOne, output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let mut groups = jo.duplicates_with_size()?;
+    for (_, group) in &mut groups {
+        group.sort_unstable();
+    }
+    groups.sort_unstable_by(|(size_a, group_a), (size_b, group_b)| {
+        let wasted = |size: u64, group: &[&Path]| size.saturating_mul(group.len() as u64 - 1);
+        wasted(*size_b as u64, group_b)
+            .cmp(&wasted(*size_a as u64, group_a))
+            .then_with(|| group_a.cmp(group_b))
+    });
+
+    output.write_all(HTML_HEAD.as_bytes())?;
+    writeln!(output, "<h1>Duplicate files</h1>")?;
+    writeln!(output, "<p>{}</p>", escape_html(&jo.summary()?.to_string()))?;
+    for (size, group) in &groups {
+        writeln!(output, "<details open>")?;
+        writeln!(
+            output,
+            "<summary>{} files, {} each</summary>",
+            group.len(),
+            justone::format_size(*size as u64)
+        )?;
+        writeln!(output, "<ul>")?;
+        for path in group {
+            let extension = path
+                .extension()
+                .map_or(String::new(), |ext| ext.to_string_lossy().to_uppercase());
+            writeln!(
+                output,
+                "<li><div class=\"thumb\">{}</div><code>{}</code></li>",
+                escape_html(&extension),
+                escape_html(&path.display().to_string())
+            )?;
+        }
+        writeln!(output, "</ul>")?;
+        writeln!(output, "</details>")?;
+    }
+    writeln!(output, "</body>\n</html>")?;
+    Ok(())
+}
+
+/// Escape the characters with a meaning in HTML text and attributes
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write `path` followed by a NUL byte, as raw bytes on Unix so any file name survives
+fn write_path0(output: &mut dyn Write, path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        output.write_all(path.as_os_str().as_bytes())?;
+    }
+    #[cfg(not(unix))]
+    output.write_all(path.to_string_lossy().as_bytes())?;
+    output.write_all(b"\0")
+}
+
+/// Delete the duplicates, or only print what would be deleted with `--dry-run`
+fn print_removals(
+    jo: &mut JustOne,
+    output: &mut dyn Write,
+    options: &Options,
+) -> Result<(), Box<dyn Error>> {
+    if options.dry_run {
+        for (i, plan) in jo.plan_removals(options.keep)?.iter().enumerate() {
+            if i != 0 {
+                writeln!(output)?;
+            }
+            writeln!(output, "[{}] Keep: {}", i + 1, plan.kept.display())?;
+            for path in &plan.remove {
+                writeln!(output, " - Remove: {}", path.display())?;
+            }
+        }
+        return Ok(());
+    }
+
+    let summary = jo.remove_duplicates(options.keep)?;
+    for path in &summary.files {
+        writeln!(output, " - Removed: {}", path.display())?;
+    }
+    writeln!(
+        output,
+        "Removed {} files, {} bytes freed.",
+        summary.files.len(),
+        summary.bytes_freed
+    )?;
     Ok(())
 }
@@ -0,0 +1,296 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    ErrorPolicy, Event, FileSize, HashAlgorithm, HasherCreator, JustOne, ProgressObserver, Result,
+    StrictLevel, SymlinkPolicy,
+};
+
+/// Configure a `JustOne` option by option, see `JustOne::builder`.
+///
+/// Every option left unset keeps its default, the same as `JustOne::new`.
+pub struct JustOneBuilder {
+    jo: JustOne,
+}
+
+impl JustOneBuilder {
+    pub fn new() -> Self {
+        JustOneBuilder {
+            jo: JustOne::default(),
+        }
+    }
+
+    /// How strictly the files sharing a hash are compared, see `StrictLevel`
+    pub fn strict_level(mut self, strict_level: StrictLevel) -> Self {
+        self.jo.strict_level = strict_level;
+        self
+    }
+
+    /// Skip the files which fail with an IO error instead of aborting, see `JustOne::ignored`.
+    /// The same as `ErrorPolicy::Ignore` if true, `ErrorPolicy::FailFast` otherwise.
+    pub fn ignore_error(mut self, ignore_error: bool) -> Self {
+        self.jo.error_policy = if ignore_error {
+            ErrorPolicy::Ignore
+        } else {
+            ErrorPolicy::FailFast
+        };
+        self
+    }
+
+    /// See `JustOne::with_error_policy`
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.jo = self.jo.with_error_policy(error_policy);
+        self
+    }
+
+    /// Create the small hash (and the full hash with `HashAlgorithm::XxHash64`) hashers
+    /// with `hasher_creator`
+    pub fn hasher(mut self, hasher_creator: HasherCreator) -> Self {
+        self.jo.hasher_creator = hasher_creator;
+        self
+    }
+
+    /// See `JustOne::with_seed`
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.jo = self.jo.with_seed(seed);
+        self
+    }
+
+    /// See `JustOne::with_algorithm`
+    pub fn algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.jo = self.jo.with_algorithm(algorithm);
+        self
+    }
+
+    /// See `JustOne::with_symlink_policy`
+    pub fn symlink_policy(mut self, symlink_policy: SymlinkPolicy) -> Self {
+        self.jo = self.jo.with_symlink_policy(symlink_policy);
+        self
+    }
+
+    /// Traverse the symlinks to folders and compare the symlinks to files by their target
+    /// content, i.e. `SymlinkPolicy::Follow`. Ignored if `ignore_symlink` is set.
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        match self.jo.symlink_policy {
+            SymlinkPolicy::Skip => {}
+            SymlinkPolicy::Follow if !follow_links => {
+                self.jo.symlink_policy = SymlinkPolicy::Compare
+            }
+            _ if follow_links => self.jo.symlink_policy = SymlinkPolicy::Follow,
+            _ => {}
+        }
+        self
+    }
+
+    /// Only traverse the symlinks whose target is under one of the folders passed to
+    /// `update`, i.e. `SymlinkPolicy::FollowInternal`. Ignored if `follow_links` or
+    /// `ignore_symlink` is set.
+    pub fn follow_internal_links(mut self, follow_internal_links: bool) -> Self {
+        match self.jo.symlink_policy {
+            SymlinkPolicy::Skip | SymlinkPolicy::Follow => {}
+            SymlinkPolicy::FollowInternal if !follow_internal_links => {
+                self.jo.symlink_policy = SymlinkPolicy::Compare
+            }
+            _ if follow_internal_links => self.jo.symlink_policy = SymlinkPolicy::FollowInternal,
+            _ => {}
+        }
+        self
+    }
+
+    /// Skip the symlinks altogether, i.e. `SymlinkPolicy::Skip`
+    pub fn ignore_symlink(mut self, ignore_symlink: bool) -> Self {
+        if ignore_symlink {
+            self.jo.symlink_policy = SymlinkPolicy::Skip;
+        } else if self.jo.symlink_policy == SymlinkPolicy::Skip {
+            self.jo.symlink_policy = SymlinkPolicy::Compare;
+        }
+        self
+    }
+
+    /// See `JustOne::with_symlink_resolve`
+    pub fn symlink_resolve(mut self, symlink_resolve: bool) -> Self {
+        self.jo = self.jo.with_symlink_resolve(symlink_resolve);
+        self
+    }
+
+    /// See `JustOne::with_max_depth`
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.jo = self.jo.with_max_depth(depth);
+        self
+    }
+
+    /// See `JustOne::with_min_depth`
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.jo = self.jo.with_min_depth(depth);
+        self
+    }
+
+    /// See `JustOne::with_excludes`
+    pub fn excludes(mut self, patterns: Vec<String>) -> Result<Self> {
+        self.jo = self.jo.with_excludes(patterns)?;
+        Ok(self)
+    }
+
+    /// See `JustOne::with_respect_gitignore`
+    pub fn respect_gitignore(mut self, respect_gitignore: bool) -> Self {
+        self.jo = self.jo.with_respect_gitignore(respect_gitignore);
+        self
+    }
+
+    /// See `JustOne::with_same_filesystem`
+    pub fn same_filesystem(mut self, same_filesystem: bool) -> Self {
+        self.jo = self.jo.with_same_filesystem(same_filesystem);
+        self
+    }
+
+    /// See `JustOne::with_extensions`
+    pub fn extensions(mut self, exts: Vec<String>) -> Self {
+        self.jo = self.jo.with_extensions(exts);
+        self
+    }
+
+    /// See `JustOne::with_small_hash_size`
+    pub fn small_hash_size(mut self, size: usize) -> Self {
+        self.jo = self.jo.with_small_hash_size(size);
+        self
+    }
+
+    /// See `JustOne::with_read_buffer_size`
+    pub fn read_buffer_size(mut self, size: usize) -> Self {
+        self.jo = self.jo.with_read_buffer_size(size);
+        self
+    }
+
+    /// See `JustOne::with_skip_small_hash`
+    pub fn skip_small_hash(mut self, skip_small_hash: bool) -> Self {
+        self.jo = self.jo.with_skip_small_hash(skip_small_hash);
+        self
+    }
+
+    /// See `JustOne::with_normalize_text`
+    pub fn normalize_text(mut self, normalize_text: bool) -> Self {
+        self.jo = self.jo.with_normalize_text(normalize_text);
+        self
+    }
+
+    /// See `JustOne::with_io_rate_limit`
+    pub fn io_rate_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.jo = self.jo.with_io_rate_limit(bytes_per_sec);
+        self
+    }
+
+    /// See `JustOne::with_full_hash_limit`
+    pub fn full_hash_limit(mut self, limit: u64) -> Self {
+        self.jo = self.jo.with_full_hash_limit(limit);
+        self
+    }
+
+    /// See `JustOne::with_cancel_token`
+    pub fn cancel_token(mut self, cancel_token: Arc<AtomicBool>) -> Self {
+        self.jo = self.jo.with_cancel_token(cancel_token);
+        self
+    }
+
+    /// See `JustOne::with_threads`
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.jo = self.jo.with_threads(threads);
+        self
+    }
+
+    /// See `JustOne::with_retry`
+    pub fn retry(mut self, count: u32, delay: Duration) -> Self {
+        self.jo = self.jo.with_retry(count, delay);
+        self
+    }
+
+    /// See `JustOne::with_canonicalize`
+    pub fn canonicalize(mut self, canonicalize: bool) -> Self {
+        self.jo = self.jo.with_canonicalize(canonicalize);
+        self
+    }
+
+    /// See `JustOne::with_case_insensitive_paths`
+    pub fn case_insensitive_paths(mut self, case_insensitive_paths: bool) -> Self {
+        self.jo = self.jo.with_case_insensitive_paths(case_insensitive_paths);
+        self
+    }
+
+    /// See `JustOne::with_mmap`
+    pub fn mmap(mut self, mmap: bool) -> Self {
+        self.jo = self.jo.with_mmap(mmap);
+        self
+    }
+
+    /// See `JustOne::with_report_hardlinks`
+    pub fn report_hardlinks(mut self, report_hardlinks: bool) -> Self {
+        self.jo = self.jo.with_report_hardlinks(report_hardlinks);
+        self
+    }
+
+    /// See `JustOne::with_ignore_empty`
+    pub fn ignore_empty(mut self, ignore_empty: bool) -> Self {
+        self.jo = self.jo.with_ignore_empty(ignore_empty);
+        self
+    }
+
+    /// See `JustOne::with_min_size`
+    pub fn min_size(mut self, size: FileSize) -> Self {
+        self.jo = self.jo.with_min_size(size);
+        self
+    }
+
+    /// See `JustOne::with_max_size`
+    pub fn max_size(mut self, size: FileSize) -> Self {
+        self.jo = self.jo.with_max_size(size);
+        self
+    }
+
+    /// See `JustOne::with_require_same_name`
+    pub fn require_same_name(mut self, require_same_name: bool) -> Self {
+        self.jo = self.jo.with_require_same_name(require_same_name);
+        self
+    }
+
+    /// See `JustOne::with_low_memory`
+    pub fn low_memory(mut self, low_memory: bool) -> Self {
+        self.jo = self.jo.with_low_memory(low_memory);
+        self
+    }
+
+    /// See `JustOne::with_event_sender`
+    pub fn event_sender(mut self, sender: Sender<Event>) -> Self {
+        self.jo = self.jo.with_event_sender(sender);
+        self
+    }
+
+    /// See `JustOne::with_perceptual_distance`
+    #[cfg(feature = "perceptual")]
+    pub fn perceptual_distance(mut self, max_distance: u32) -> Self {
+        self.jo = self.jo.with_perceptual_distance(max_distance);
+        self
+    }
+
+    /// See `JustOne::with_progress_observer`
+    pub fn progress_observer(mut self, observer: impl ProgressObserver + 'static) -> Self {
+        self.jo = self.jo.with_progress_observer(observer);
+        self
+    }
+
+    /// See `JustOne::set_progress`
+    pub fn show_progress(mut self, show_progress: bool) -> Self {
+        self.jo.set_progress(show_progress);
+        self
+    }
+
+    pub fn build(self) -> JustOne {
+        self.jo
+    }
+}
+
+impl Default for JustOneBuilder {
+    fn default() -> Self {
+        JustOneBuilder::new()
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{self, Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FileSize, FullHash, HashAlgorithm, JustOne, JustOneError, Result, SmallHash};
+
+/// Hashes of a file, valid as long as its size and modified time don't change
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    size: FileSize,
+    modified: SystemTime,
+    small_hash: Option<SmallHash>,
+    full_hash: Option<FullHash>,
+}
+
+/// Content of a cache file, hashes computed with another hasher, algorithm, small hash size,
+/// full hash limit or text normalization are not comparable
+#[derive(Debug, Deserialize)]
+struct CacheFile {
+    hasher_fingerprint: u64,
+    hash_algorithm: HashAlgorithm,
+    small_hash_size: usize,
+    full_hash_limit: Option<u64>,
+    normalize_text: bool,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+/// Same layout as `CacheFile`, borrowing the entries to save
+#[derive(Debug, Serialize)]
+struct CacheFileRef<'a> {
+    hasher_fingerprint: u64,
+    hash_algorithm: HashAlgorithm,
+    small_hash_size: usize,
+    full_hash_limit: Option<u64>,
+    normalize_text: bool,
+    entries: HashMap<&'a Path, &'a CacheEntry>,
+}
+
+pub(crate) type HashCache = HashMap<PathBuf, CacheEntry>;
+
+impl JustOne {
+    /// Load the hashes saved by `save_cache`, files whose size and modified time did not change
+    /// since are not read again by the following `update` calls.
+    ///
+    /// A cache saved with another hasher creator (or seed), `HashAlgorithm`, small hash size,
+    /// full hash limit or text normalization is discarded.
+    pub fn load_cache(&mut self, cache_file: impl AsRef<Path>) -> Result<&mut Self> {
+        let cache_file = cache_file.as_ref();
+        let f = File::open(cache_file).map_err(|e| io_error!(e, cache_file))?;
+        let cache: CacheFile = bincode::deserialize_from(BufReader::new(f))
+            .map_err(|e| io_error!(io::Error::new(io::ErrorKind::InvalidData, e), cache_file))?;
+        if cache.hasher_fingerprint == self.hasher_fingerprint()
+            && cache.hash_algorithm == self.hash_algorithm
+            && cache.small_hash_size == self.small_hash_size
+            && cache.full_hash_limit == self.full_hash_limit
+            && cache.normalize_text == self.normalize_text
+        {
+            self.cache = cache.entries;
+        }
+        Ok(self)
+    }
+
+    /// Save the hashes of the indexed files, along with the loaded ones of the files which
+    /// were not scanned again, for a later `load_cache`.
+    pub fn save_cache(&self, cache_file: impl AsRef<Path>) -> Result<()> {
+        let cache_file = cache_file.as_ref();
+        let mut entries: HashMap<&Path, &CacheEntry> = self
+            .cache
+            .iter()
+            .map(|(path, entry)| (path.as_ref(), entry))
+            .collect();
+        let scanned: Vec<(PathBuf, CacheEntry)> = self
+            .file_infos()
+            .filter_map(|file_info| {
+                let modified = file_info.modified?;
+                if file_info.small_hash.is_none() && file_info.full_hash.is_none() {
+                    return None;
+                }
+                let path = path::absolute(&file_info.path).ok()?;
+                let entry = CacheEntry {
+                    size: file_info.size,
+                    modified,
+                    small_hash: file_info.small_hash,
+                    full_hash: file_info.full_hash.clone(),
+                };
+                Some((path, entry))
+            })
+            .collect();
+        entries.extend(scanned.iter().map(|(path, entry)| (path.as_ref(), entry)));
+
+        let f = File::create(cache_file).map_err(|e| io_error!(e, cache_file))?;
+        let cache = CacheFileRef {
+            hasher_fingerprint: self.hasher_fingerprint(),
+            hash_algorithm: self.hash_algorithm,
+            small_hash_size: self.small_hash_size,
+            full_hash_limit: self.full_hash_limit,
+            normalize_text: self.normalize_text,
+            entries,
+        };
+        bincode::serialize_into(BufWriter::new(f), &cache)
+            .map_err(|e| io_error!(io::Error::other(e), cache_file))
+    }
+
+    /// Return the cached hashes of the file if its size and modified time still match
+    pub(crate) fn cached_hashes(
+        &self,
+        path: &Path,
+        size: FileSize,
+        modified: Option<SystemTime>,
+    ) -> (Option<SmallHash>, Option<FullHash>) {
+        if self.cache.is_empty() {
+            return (None, None);
+        }
+        let entry = path::absolute(path)
+            .ok()
+            .and_then(|path| self.cache.get(&path));
+        match entry {
+            Some(entry) if entry.size == size && Some(entry.modified) == modified => {
+                (entry.small_hash, entry.full_hash.clone())
+            }
+            _ => (None, None),
+        }
+    }
+}
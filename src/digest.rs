@@ -0,0 +1,46 @@
+use std::hash::Hasher;
+
+use sha2::Sha256;
+
+/// A hash function fed by chunks, whose result may be wider than the 64 bits of a
+/// `std::hash::Hasher`
+pub(crate) trait Digest {
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Consume the digest and return the hash bytes, in the byte order of the algorithm
+    fn finalize(self) -> Vec<u8>;
+}
+
+/// Adapter running a `Hasher` of the hasher creator, e.g. the default `XxHash64`, as a
+/// 64-bit `Digest` emitting the little-endian bytes of the hash value
+pub(crate) struct HasherDigest(pub(crate) Box<dyn Hasher>);
+
+impl Digest for HasherDigest {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.0.finish().to_le_bytes().to_vec()
+    }
+}
+
+impl Digest for Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        sha2::Digest::update(self, bytes);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        sha2::Digest::finalize(self).to_vec()
+    }
+}
+
+impl Digest for blake3::Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        blake3::Hasher::update(self, bytes);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        blake3::Hasher::finalize(&self).as_bytes().to_vec()
+    }
+}
@@ -0,0 +1,76 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::{
+    error_kind, ErrorPolicy, FileSize, IgnoredFile, JustOne, JustOneError, Phase, Result, Stats,
+};
+
+/// What a scan reports to the sender given to `JustOne::with_event_sender`, as it happens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A pass of `update` starts
+    PhaseStarted(Phase),
+    /// A regular file was found and indexed, before any hashing. Sent once per file: not
+    /// for a file found again by a later `update`, nor for another hardlink to a file
+    /// already indexed
+    FileFound { path: PathBuf, size: FileSize },
+    /// The full hash of a file has been computed
+    FileHashed { path: PathBuf, size: FileSize },
+    /// A group of duplicates, sent by `JustOne::scan` once the folder is indexed
+    DuplicateGroupFound { paths: Vec<PathBuf> },
+    /// A file was skipped because of an error, see `JustOne::ignored`
+    Skipped { path: PathBuf, kind: io::ErrorKind },
+    /// `JustOne::scan` is done
+    Finished { stats: Stats },
+}
+
+impl JustOne {
+    /// Send the events of the scans to `sender`, e.g. to render them live in another
+    /// thread. The events are dropped once the receiver is gone.
+    pub fn with_event_sender(mut self, sender: Sender<Event>) -> Self {
+        self.event_sender = Some(sender);
+        self
+    }
+
+    /// Index the folder `dir` like `update`, then send each duplicate group and a
+    /// `Event::Finished` to the event sender, if any, and return the duplicate groups.
+    pub fn scan(&mut self, dir: impl AsRef<Path>) -> Result<Vec<Vec<PathBuf>>> {
+        self.update(dir)?;
+        let dups = self.duplicates_owned()?;
+        for paths in &dups {
+            self.send_event(|| Event::DuplicateGroupFound {
+                paths: paths.clone(),
+            });
+        }
+        self.send_event(|| Event::Finished {
+            stats: self.stats(),
+        });
+        Ok(dups)
+    }
+
+    /// Send the event built by `event` if there is an event sender
+    pub(crate) fn send_event(&self, event: impl FnOnce() -> Event) {
+        if let Some(sender) = &self.event_sender {
+            // the receiver may be gone, the scan goes on without it
+            let _ = sender.send(event());
+        }
+    }
+
+    /// Record a file skipped because of `error`, keeping the error with
+    /// `ErrorPolicy::Collect`
+    pub(crate) fn add_ignored(&mut self, path: PathBuf, error: JustOneError) {
+        let ignored = IgnoredFile {
+            path,
+            kind: error_kind(&error),
+        };
+        self.send_event(|| Event::Skipped {
+            path: ignored.path.clone(),
+            kind: ignored.kind,
+        });
+        self.ignored_files.push(ignored);
+        if self.error_policy == ErrorPolicy::Collect {
+            self.errors.push(error);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use walkdir::DirEntry;
+
+/// The names of the ignore files read in every folder, the later ones taking precedence
+const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The ignore files of the folders above the current entry of a depth-first traversal
+pub(crate) struct GitignoreStack {
+    /// The matchers of the folders, with the shallowest traversal depth they apply to
+    matchers: Vec<(usize, Gitignore)>,
+}
+
+impl GitignoreStack {
+    /// Start a traversal of `path` in the folder `root_dir`, the ignore files of the
+    /// folders from `root_dir` down to the parent of `path` apply to the whole traversal
+    pub(crate) fn new(root_dir: &Path, path: &Path) -> Self {
+        let mut stack = GitignoreStack {
+            matchers: Vec::new(),
+        };
+        if let Ok(relative_path) = path.strip_prefix(root_dir) {
+            let mut dir = root_dir.to_owned();
+            for component in relative_path.components() {
+                stack.push(&dir, 0);
+                dir.push(component);
+            }
+        }
+        stack
+    }
+
+    /// Return true if `entry` is ignored by the ignore files above it. A folder which isn't
+    /// ignored has its own ignore files read for the entries below it.
+    pub(crate) fn is_ignored(&mut self, entry: &DirEntry) -> bool {
+        let depth = entry.depth();
+        while let Some((min_depth, _)) = self.matchers.last() {
+            if *min_depth <= depth {
+                break;
+            }
+            self.matchers.pop();
+        }
+
+        let is_dir = entry.file_type().is_dir();
+        let ignored = self
+            .matchers
+            .iter()
+            .rev()
+            .map(|(_, matcher)| matcher.matched(entry.path(), is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore());
+        if !ignored && is_dir {
+            self.push(entry.path(), depth + 1);
+        }
+        ignored
+    }
+
+    /// Read the ignore files of `dir`, applying from the traversal depth `min_depth`
+    fn push(&mut self, dir: &Path, min_depth: usize) {
+        let mut builder = GitignoreBuilder::new(dir);
+        for name in &IGNORE_FILE_NAMES {
+            let path = dir.join(name);
+            if path.is_file() {
+                // the valid lines still apply when some are not
+                let _ = builder.add(path);
+            }
+        }
+        if let Ok(matcher) = builder.build() {
+            if !matcher.is_empty() {
+                self.matchers.push((min_depth, matcher));
+            }
+        }
+    }
+}
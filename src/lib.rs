@@ -1,60 +1,303 @@
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::fs::{self, File};
-use std::io;
-use std::path::{Path, PathBuf};
+use std::io::{self, Read};
+use std::panic;
+use std::path::{Component, Path, PathBuf};
 use std::result;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use memmap2::Mmap;
 use walkdir::{DirEntry, WalkDir};
 
-use std::hash::Hasher;
+use std::hash::{Hash, Hasher};
 use twox_hash::XxHash64;
 
-use indicatif::ProgressIterator;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 
-use filecmp;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
-const FOLLOW_LINKS_DEFAULT: bool = false;
-const IGNORE_ERROR_DEFAULT: bool = false;
-const IGNORE_SYMLINK_DEFAULT: bool = false;
 const XXHASH_SEED_DEFAULT: u64 = 0;
-const FILE_READ_BUFFER_SIZE: usize = 8192;
-const SMALL_HASH_CHUNK_SIZE: usize = 1024;
+const READ_BUFFER_SIZE_DEFAULT: usize = 128 * 1024;
+const SMALL_HASH_SIZE_DEFAULT: usize = 1024;
+/// Number of leading digest bytes kept as the small hash
+const SMALL_HASH_LEN: usize = 8;
+const CANONICALIZE_DEFAULT: bool = false;
+const IGNORE_EMPTY_DEFAULT: bool = false;
+const REQUIRE_SAME_NAME_DEFAULT: bool = false;
+const LOW_MEMORY_DEFAULT: bool = false;
+const MMAP_DEFAULT: bool = false;
+const REPORT_HARDLINKS_DEFAULT: bool = false;
+const NORMALIZE_TEXT_DEFAULT: bool = false;
+const SYMLINK_RESOLVE_DEFAULT: bool = false;
+const RESPECT_GITIGNORE_DEFAULT: bool = false;
+const SAME_FILESYSTEM_DEFAULT: bool = false;
+const CASE_INSENSITIVE_PATHS_DEFAULT: bool = false;
+#[cfg(feature = "perceptual")]
+const PERCEPTUAL_DISTANCE_DEFAULT: u32 = 10;
+/// Larger files are read through a buffer even with mmap on, so they can't exhaust the
+/// address space of a 32-bit process
+const MMAP_MAX_SIZE: u64 = if cfg!(target_pointer_width = "64") {
+    isize::MAX as u64
+} else {
+    256 * 1024 * 1024
+};
+/// How many entries the traversals of `update_all` can find ahead of the indexing
+const WALK_CHANNEL_CAPACITY: usize = 1024;
+const RETRY_COUNT_DEFAULT: u32 = 0;
+const RETRY_DELAY_DEFAULT: Duration = Duration::from_millis(100);
 
 type SizeDict = HashMap<FileSize, HashSet<FileIndex>>;
 type SmallHashDict = HashMap<(FileSize, SmallHash), HashSet<FileIndex>>;
 type FullHashDict = HashMap<FullHash, HashSet<FileIndex>>;
 type SymlinkHashDict = HashMap<SymlinkContent, HashSet<SymlinkPath>>;
+/// The first file of each list stands for the others in the size and hash dicts
+type HardlinkDict = HashMap<FileId, Vec<FileIndex>>;
+type DuplicateGroups<'a> = Box<dyn Iterator<Item = Result<Vec<&'a Path>>> + 'a>;
 
 pub type Result<T> = result::Result<T, JustOneError>;
 
+/// Create a new hasher for each file, shared by the hashing threads
+pub type HasherCreator = Box<dyn Fn() -> Box<dyn Hasher> + Send + Sync>;
+
 pub struct JustOne {
-    hasher_creator: Box<dyn Fn() -> Box<dyn Hasher>>,
+    hasher_creator: HasherCreator,
+    hash_algorithm: HashAlgorithm,
     strict_level: StrictLevel,
-    /// If true, PermissionDenied or other IO Error will be ignored
-    ignore_error: bool,
-    /// Files which were ignored if `ignore_error` is true
-    ignored_files: Vec<PathBuf>,
-    /// If true, symlink-type file will be ignored, and `follow_links` will be set false
-    ignore_symlink: bool,
-    /// If true, it will traverse symbolic link to dest file when deal with symlink
-    follow_links: bool,
-    file_info: Vec<FileInfo>,
+    /// What a scan does when a file fails with an IO error (PermissionDenied...)
+    error_policy: ErrorPolicy,
+    /// Files which were skipped because of an error, unless `error_policy` is `FailFast`
+    ignored_files: Vec<IgnoredFile>,
+    /// The errors of the skipped files, with `ErrorPolicy::Collect`
+    errors: Vec<JustOneError>,
+    /// Regular files skipped for being larger than `max_size`, each listed once
+    too_large_files: Vec<PathBuf>,
+    /// The paths of `too_large_files`, so a file met again by another scan isn't listed twice
+    too_large_paths: HashSet<PathBuf>,
+    /// How transient IO errors are retried while hashing
+    retry_policy: RetryPolicy,
+    /// Stop the current `update` as soon as it is set
+    cancel_token: Option<Arc<AtomicBool>>,
+    /// Hash files on this pool if set, serially otherwise
+    thread_pool: Option<ThreadPool>,
+    /// How many bytes from the start of the files the small hash covers
+    small_hash_size: usize,
+    /// How many bytes are read at once while computing the full hashes
+    read_buffer_size: usize,
+    /// Throttles the reads of the hashing passes, if set
+    io_rate_limit: Option<RateLimiter>,
+    /// If set, the full hashes only cover this many bytes from the start of the files, so
+    /// the duplicates are only candidates
+    full_hash_limit: Option<u64>,
+    /// If true, files are indexed by their canonical path, so a file reached through several
+    /// paths (overlapping folders, `..`, symlinked folders) is indexed once
+    canonicalize: bool,
+    /// If true, paths differing only in case are the same file in `file_index`
+    case_insensitive_paths: bool,
+    /// If true, zero-byte files are not indexed
+    ignore_empty: bool,
+    /// Regular files smaller than this many bytes are skipped, if set
+    min_size: Option<FileSize>,
+    /// Regular files larger than this many bytes are skipped and listed in `too_large_files`,
+    /// if set
+    max_size: Option<FileSize>,
+    /// If true, only the files with the same file name are duplicates
+    require_same_name: bool,
+    /// If true, files with a unique size are dropped from the index right after the size pass
+    low_memory: bool,
+    /// Whether the size candidates get their full hash right away instead of a small hash
+    /// first, decided by their size if unset
+    skip_small_hash: Option<bool>,
+    /// If true, the line endings and trailing blanks of text files are normalized before
+    /// hashing, and the files are no longer told apart by their size
+    normalize_text: bool,
+    /// If true, the full hashes are computed on the files mapped in memory
+    mmap: bool,
+    /// If true, the hardlinks to a same file are compared as separate files
+    report_hardlinks: bool,
+    /// Whether the symlinks are skipped, compared as links or traversed
+    symlink_policy: SymlinkPolicy,
+    /// If true, symlinks are grouped by the canonical path of their target instead of the
+    /// target resolved lexically from the folder of the link
+    symlink_resolve: bool,
+    /// If true, the paths ignored by the `.gitignore` and `.ignore` files are skipped
+    respect_gitignore: bool,
+    /// If true, the traversal doesn't descend into folders on another filesystem
+    same_filesystem: bool,
+    /// Lowercase extensions (without the dot) of the only regular files indexed, if set
+    extensions: Option<HashSet<String>>,
+    /// Deepest level of a folder to traverse, the folder itself being at depth 0
+    max_depth: Option<usize>,
+    /// Shallowest level of a folder to yield entries from
+    min_depth: Option<usize>,
+    /// Entries matching these patterns, relative to the scanned folder, are skipped
+    excludes: Option<GlobSet>,
+    /// Directories passed to `update`, `FileInfo` refers to them by index
+    roots: Vec<PathBuf>,
+    /// The roots only holding the files given to `update_files`, never scanned as a whole
+    file_roots: HashSet<RootIndex>,
+    /// Files under these directories must never be deleted or modified by any action
+    readonly_roots: Vec<PathBuf>,
+    /// Indexed by `FileIndex`, `None` for the files dropped from the index
+    file_info: Vec<Option<FileInfo>>,
     file_index: HashMap<PathBuf, FileIndex>,
     size_dict: SizeDict,
     small_hash_dict: SmallHashDict,
     full_hash_dict: FullHashDict,
     symlink_hash_dict: SymlinkHashDict,
+    hardlink_dict: HardlinkDict,
+    progress: Arc<ProgressCounters>,
+    /// Receives the progress of each pass of `update`
+    progress_observer: Box<dyn ProgressObserver>,
+    /// Largest Hamming distance between the perceptual hashes of two similar images
+    #[cfg(feature = "perceptual")]
+    perceptual_distance: u32,
+    /// Receives the events of the scans, if set
+    event_sender: Option<Sender<Event>>,
+    /// Hashes loaded by `load_cache`, keyed by absolute path
+    cache: HashCache,
 }
 
-#[derive(Debug)]
+/// The algorithm of the full hash, the small hash prefilter always uses the hasher creator
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    /// 64 bits from the hasher creator, fast but collisions are possible on large datasets
+    #[default]
+    XxHash64,
+    /// 256 bits cryptographic digest, slow
+    Sha256,
+    /// 256 bits cryptographic digest, much faster than SHA-256
+    Blake3,
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            HashAlgorithm::XxHash64 => "xxh64",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        })
+    }
+}
+
+#[derive(Debug, Default)]
 pub enum StrictLevel {
+    #[default]
     Common,
+    /// Like `Common`, then compare the files of each group byte by byte to split the groups
+    /// of files whose hashes collide
+    CommonVerified,
     Shallow,
     ByteByByte,
 }
 
+/// What a scan does with the symlinks it meets
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Skip the symlinks altogether
+    Skip,
+    /// Compare the symlinks with each other by their target, never with the regular files
+    #[default]
+    Compare,
+    /// Traverse the symlinks to folders and compare the symlinks to files by their target
+    /// content
+    Follow,
+    /// Like `Follow`, but only for the symlinks whose target is under one of the folders
+    /// passed to `update`, so a link to e.g. `/usr` can't pull a whole system folder into the
+    /// scan. The other symlinks are skipped.
+    FollowInternal,
+}
+
+/// What a scan does when a file fails with an IO error, e.g. a permission error
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Stop at the first error and return it
+    #[default]
+    FailFast,
+    /// Skip the file, listing it in `JustOne::ignored` and keeping the whole error in
+    /// `JustOne::errors`, so everything that went wrong can be reviewed after the scan
+    Collect,
+    /// Skip the file, only listing it in `JustOne::ignored`
+    Ignore,
+}
+
+/// Retry transient IO errors (interrupted, timed out...) `count` times, waiting `delay`
+/// before the first retry and doubling it for each following one.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub count: u32,
+    pub delay: Duration,
+}
+
+/// A file skipped because of an error, unless the error policy is `ErrorPolicy::FailFast`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IgnoredFile {
+    pub path: PathBuf,
+    /// Tells a permission error from a missing file, `Other` if it wasn't an IO error
+    #[serde(serialize_with = "report::serialize_error_kind")]
+    pub kind: io::ErrorKind,
+}
+
+/// The timestamps of an indexed file, see `JustOne::file_times`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileTimes {
+    pub modified: Option<SystemTime>,
+    /// `None` where the platform or the filesystem doesn't record it
+    pub created: Option<SystemTime>,
+}
+
+/// Counters of the work done so far, shared with `ProgressHandle`
+#[derive(Debug, Default)]
+struct ProgressCounters {
+    files_walked: AtomicU64,
+    files_hashed: AtomicU64,
+    bytes_read: AtomicU64,
+    files_pending: AtomicU64,
+    size_candidates: AtomicU64,
+    small_hashes: AtomicU64,
+}
+
+/// How far a scan went, see `JustOne::progress_snapshot`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressSnapshot {
+    /// Regular files and symlinks met during the directory traversal
+    pub files_walked: u64,
+    /// Files whose full hash has been computed
+    pub files_hashed: u64,
+    /// Bytes read from the files while hashing
+    pub bytes_read: u64,
+    /// Candidate files not hashed yet in the current hashing pass
+    pub files_pending: u64,
+}
+
+/// How much work all the scans did so far, see `JustOne::stats`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// Regular files and symlinks met during the directory traversal
+    pub files_seen: u64,
+    /// Files sharing their size with another file, i.e. passing the size prefilter
+    pub size_candidates: u64,
+    /// Small hashes computed, not counting the ones from the cache
+    pub small_hashes: u64,
+    /// Full hashes computed, not counting the ones from the cache
+    pub full_hashes: u64,
+    /// Bytes read from the files while hashing
+    pub bytes_read: u64,
+}
+
+/// A handle to poll the progress of a `JustOne` from another thread while it is scanning
+#[derive(Debug, Clone)]
+pub struct ProgressHandle(Arc<ProgressCounters>);
+
 #[derive(Debug)]
 pub enum JustOneError {
     IOError {
@@ -62,13 +305,23 @@ pub enum JustOneError {
         error: io::Error,
     },
     WalkdirError(walkdir::Error),
+    /// An exclude pattern is not a valid glob
+    PatternError(globset::Error),
+    /// An action tried to modify a file under a root marked as read-only
+    ReadOnlyError(PathBuf),
+    /// The cancel token was set during an `update`
+    Cancelled,
+    /// `merge` was given an index whose hashes are not comparable, because of the named
+    /// setting
+    IncompatibleIndex(&'static str),
+    /// The filesystem watcher failed
+    #[cfg(feature = "watch")]
+    WatchError(notify::Error),
 }
 
 macro_rules! io_error {
     ($err:expr $(, $file:expr) *) => {{
-        #[cfg(debug_assertions)]
-        // '\n' in tail for the printed-line covering by '\r'
-        eprintln!("[DEBUG:io_error!] {}:{}:{}\n", file!(), line!(), column!());
+        log::debug!("io_error! at {}:{}:{}", file!(), line!(), column!());
         JustOneError::IOError {
             files: vec![$(($file.as_ref() as &Path).to_path_buf(),)*],
             error: $err,
@@ -78,18 +331,36 @@ macro_rules! io_error {
 
 macro_rules! walkdir_error {
     ($err:expr) => {{
-        #[cfg(debug_assertions)]
-        // '\n' in tail for the printed-line covering by '\r'
-        eprintln!(
-            "[DEBUG:walkdir_error!] {}:{}:{}\n",
-            file!(),
-            line!(),
-            column!()
-        );
+        log::debug!("walkdir_error! at {}:{}:{}", file!(), line!(), column!());
         JustOneError::WalkdirError($err)
     }};
 }
 
+mod action;
+mod builder;
+mod cache;
+mod digest;
+mod event;
+mod gitignore;
+#[cfg(feature = "perceptual")]
+mod perceptual;
+mod progress;
+mod report;
+mod text;
+mod throttle;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use action::{ActionSummary, KeepStrategy, RemovalPlan};
+pub use builder::JustOneBuilder;
+use cache::HashCache;
+use digest::{Digest, HasherDigest};
+pub use event::Event;
+use gitignore::GitignoreStack;
+pub use progress::{BarObserver, NoopObserver, Phase, ProgressObserver};
+pub use report::{format_size, DuplicateGroup, HashedGroup, Report, Summary};
+use throttle::RateLimiter;
+
 impl fmt::Display for JustOneError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -110,6 +381,16 @@ impl fmt::Display for JustOneError {
                 error.fmt(f)
             }
             JustOneError::WalkdirError(e) => e.fmt(f),
+            JustOneError::PatternError(e) => e.fmt(f),
+            JustOneError::ReadOnlyError(path) => {
+                write!(f, "`{}` is under a read-only root", path.display())
+            }
+            JustOneError::Cancelled => write!(f, "the scan was cancelled"),
+            JustOneError::IncompatibleIndex(setting) => {
+                write!(f, "the indexes can't be merged, their {} differ", setting)
+            }
+            #[cfg(feature = "watch")]
+            JustOneError::WatchError(e) => e.fmt(f),
         }
     }
 }
@@ -119,6 +400,12 @@ impl Error for JustOneError {
         match self {
             JustOneError::IOError { files: _, error } => Some(error),
             JustOneError::WalkdirError(e) => Some(e),
+            JustOneError::PatternError(e) => Some(e),
+            JustOneError::ReadOnlyError(_)
+            | JustOneError::Cancelled
+            | JustOneError::IncompatibleIndex(_) => None,
+            #[cfg(feature = "watch")]
+            JustOneError::WatchError(e) => Some(e),
         }
     }
 }
@@ -132,6 +419,12 @@ impl From<io::Error> for JustOneError {
     }
 }
 
+impl From<globset::Error> for JustOneError {
+    fn from(err: globset::Error) -> Self {
+        JustOneError::PatternError(err)
+    }
+}
+
 impl From<walkdir::Error> for JustOneError {
     fn from(err: walkdir::Error) -> Self {
         JustOneError::WalkdirError(err)
@@ -140,55 +433,183 @@ impl From<walkdir::Error> for JustOneError {
 
 #[derive(Debug)]
 struct FileInfo {
-    id: FileIndex,
     path: PathBuf,
+    root: RootIndex,
     size: FileSize,
+    modified: Option<SystemTime>,
+    created: Option<SystemTime>,
     small_hash: Option<SmallHash>,
     full_hash: Option<FullHash>,
+    /// The key of the file in `hardlink_dict`, if it is listed there
+    file_id: Option<FileId>,
+}
+
+/// An entry found by a folder traversal, with only what the index needs of it, so the
+/// entries are indexed as they come rather than kept until the traversal is done
+enum Found {
+    File {
+        path: PathBuf,
+        size: FileSize,
+        times: FileTimes,
+        file_id: Option<FileId>,
+    },
+    Symlink(PathBuf),
+    /// An entry skipped because of `JustOneError`
+    Ignored(PathBuf, JustOneError),
+}
+
+impl Found {
+    /// The regular file at `path`, with its `metadata`
+    fn file(path: PathBuf, metadata: &fs::Metadata) -> Self {
+        Found::File {
+            path,
+            size: metadata.len() as FileSize,
+            times: FileTimes {
+                modified: metadata.modified().ok(),
+                created: metadata.created().ok(),
+            },
+            file_id: get_file_id(metadata),
+        }
+    }
 }
 
 type FileIndex = usize;
-type FileSize = usize;
+type RootIndex = usize;
+/// (device, inode) identifying the file content on Unix
+type FileId = (u64, u64);
+pub type FileSize = usize;
 type SymlinkContent = PathBuf;
 type SymlinkPath = PathBuf;
-#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
-struct SmallHash(u64);
-#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
-struct FullHash(u64);
+/// The leading bytes of the digest of the start of the file, only a prefilter for the full
+/// hash so it stays small whatever the `HashAlgorithm`
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
+struct SmallHash([u8; SMALL_HASH_LEN]);
+/// The digest of the whole file content, its length depends on the `HashAlgorithm`
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Serialize, Deserialize)]
+struct FullHash(Vec<u8>);
+
+impl fmt::LowerHex for FullHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
 
 impl Default for JustOne {
     fn default() -> Self {
-        let ignore_symlink = IGNORE_SYMLINK_DEFAULT;
-        let follow_links = if ignore_symlink {
-            false
-        } else {
-            FOLLOW_LINKS_DEFAULT
-        };
         JustOne {
             hasher_creator: Box::new(|| Box::new(XxHash64::with_seed(XXHASH_SEED_DEFAULT))),
+            hash_algorithm: HashAlgorithm::default(),
             strict_level: StrictLevel::default(),
-            follow_links,
-            ignore_error: IGNORE_ERROR_DEFAULT,
+            symlink_policy: SymlinkPolicy::default(),
+            error_policy: ErrorPolicy::default(),
             ignored_files: Vec::new(),
-            ignore_symlink,
+            errors: Vec::new(),
+            too_large_files: Vec::new(),
+            too_large_paths: HashSet::new(),
+            retry_policy: RetryPolicy::default(),
+            cancel_token: None,
+            thread_pool: None,
+            small_hash_size: SMALL_HASH_SIZE_DEFAULT,
+            read_buffer_size: READ_BUFFER_SIZE_DEFAULT,
+            io_rate_limit: None,
+            full_hash_limit: None,
+            canonicalize: CANONICALIZE_DEFAULT,
+            case_insensitive_paths: CASE_INSENSITIVE_PATHS_DEFAULT,
+            ignore_empty: IGNORE_EMPTY_DEFAULT,
+            min_size: None,
+            max_size: None,
+            require_same_name: REQUIRE_SAME_NAME_DEFAULT,
+            low_memory: LOW_MEMORY_DEFAULT,
+            skip_small_hash: None,
+            normalize_text: NORMALIZE_TEXT_DEFAULT,
+            mmap: MMAP_DEFAULT,
+            report_hardlinks: REPORT_HARDLINKS_DEFAULT,
+            symlink_resolve: SYMLINK_RESOLVE_DEFAULT,
+            respect_gitignore: RESPECT_GITIGNORE_DEFAULT,
+            same_filesystem: SAME_FILESYSTEM_DEFAULT,
+            extensions: None,
+            max_depth: None,
+            min_depth: None,
+            excludes: None,
+            roots: Vec::new(),
+            file_roots: HashSet::new(),
+            readonly_roots: Vec::new(),
             file_info: Vec::new(),
             file_index: HashMap::new(),
             size_dict: HashMap::new(),
             small_hash_dict: HashMap::new(),
             full_hash_dict: HashMap::new(),
             symlink_hash_dict: HashMap::new(),
+            hardlink_dict: HashMap::new(),
+            progress: Arc::new(ProgressCounters::default()),
+            progress_observer: Box::new(NoopObserver),
+            #[cfg(feature = "perceptual")]
+            perceptual_distance: PERCEPTUAL_DISTANCE_DEFAULT,
+            event_sender: None,
+            cache: HashCache::new(),
         }
     }
 }
 
-impl Default for StrictLevel {
+impl Default for RetryPolicy {
     fn default() -> Self {
-        StrictLevel::Common
+        RetryPolicy {
+            count: RETRY_COUNT_DEFAULT,
+            delay: RETRY_DELAY_DEFAULT,
+        }
+    }
+}
+
+impl ProgressCounters {
+    fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            files_walked: self.files_walked.load(Ordering::Relaxed),
+            files_hashed: self.files_hashed.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            files_pending: self.files_pending.load(Ordering::Relaxed),
+        }
+    }
+
+    fn stats(&self) -> Stats {
+        Stats {
+            files_seen: self.files_walked.load(Ordering::Relaxed),
+            size_candidates: self.size_candidates.load(Ordering::Relaxed),
+            small_hashes: self.small_hashes.load(Ordering::Relaxed),
+            full_hashes: self.files_hashed.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+        }
+    }
+
+    fn start_pass(&self, candidates: usize) {
+        self.files_pending
+            .store(candidates as u64, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        for counter in &[
+            &self.files_walked,
+            &self.files_hashed,
+            &self.bytes_read,
+            &self.files_pending,
+            &self.size_candidates,
+            &self.small_hashes,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl ProgressHandle {
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        self.0.snapshot()
     }
 }
 
 /// Return a default hasher creator (XxHash64 with constant int seed)
-pub fn default_hasher_creator() -> Box<dyn Fn() -> Box<dyn Hasher>> {
+pub fn default_hasher_creator() -> HasherCreator {
     Box::new(|| Box::new(XxHash64::with_seed(XXHASH_SEED_DEFAULT)))
 }
 
@@ -197,366 +618,2261 @@ impl JustOne {
         JustOne::default()
     }
 
+    /// Start configuring a `JustOne` option by option
+    pub fn builder() -> JustOneBuilder {
+        JustOneBuilder::new()
+    }
+
     pub fn with_config(strict_level: StrictLevel, ignore_error: bool) -> Self {
-        JustOne {
-            hasher_creator: default_hasher_creator(),
-            strict_level,
-            ignore_error,
-            ..JustOne::default()
-        }
+        JustOne::builder()
+            .strict_level(strict_level)
+            .ignore_error(ignore_error)
+            .build()
     }
 
     pub fn with_full_config(
-        hasher_creator: Box<dyn Fn() -> Box<dyn Hasher>>,
+        hasher_creator: HasherCreator,
         strict_level: StrictLevel,
         ignore_error: bool,
     ) -> Self {
-        JustOne {
-            hasher_creator,
-            strict_level,
-            ignore_error,
-            ..JustOne::default()
-        }
+        JustOne::builder()
+            .hasher(hasher_creator)
+            .strict_level(strict_level)
+            .ignore_error(ignore_error)
+            .build()
     }
 
-    pub fn update(&mut self, dir: impl AsRef<Path>) -> Result<&mut Self> {
-        self.update_directory(dir)?;
+    /// Choose what a scan does when a file fails with an IO error: stop with the error, or
+    /// skip the file and go on, keeping the error for `errors` or not. `ignore_error` of the
+    /// builder picks between `FailFast` and `Ignore`.
+    pub fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
 
-        Ok(self)
+    /// Retry opening and hashing a file up to `count` times when it fails with a transient
+    /// IO error, which happens on flaky network mounts. `NotFound`, `PermissionDenied` and
+    /// other persistent errors are never retried.
+    pub fn with_retry(mut self, count: u32, delay: Duration) -> Self {
+        self.retry_policy = RetryPolicy { count, delay };
+        self
     }
 
-    pub fn duplicates(&self) -> Result<Vec<Vec<&Path>>> {
-        let duplicate_files = match self.strict_level {
-            StrictLevel::Common => self.duplicates_common()?,
-            StrictLevel::Shallow => self.duplicates_strict(true)?,
-            StrictLevel::ByteByByte => self.duplicates_strict(false)?,
-        };
-        if !self.ignore_symlink && !self.follow_links {
-            let duplicate_symlinks = self.duplicates_symlink();
-            Ok([duplicate_files, duplicate_symlinks].concat())
-        } else {
-            Ok(duplicate_files)
+    /// Skip the files and directories matching any of the glob `patterns`, the directories
+    /// are not descended into. Patterns are matched against the path relative to the scanned
+    /// folder, `*` does not cross `/` but `**` does, and a pattern without `/` matches a file
+    /// name at any depth (e.g. `node_modules`, `*.tmp`).
+    pub fn with_excludes(mut self, patterns: Vec<String>) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &patterns {
+            let pattern = if pattern.contains('/') {
+                pattern.trim_start_matches('/').to_owned()
+            } else {
+                format!("**/{}", pattern)
+            };
+            builder.add(GlobBuilder::new(&pattern).literal_separator(true).build()?);
         }
+        self.excludes = Some(builder.build()?);
+        Ok(self)
     }
 
-    fn duplicates_common(&self) -> Result<Vec<Vec<&Path>>> {
-        Ok(self
-            .full_hash_dict
-            .iter()
-            .filter(|(_, v)| v.len() > 1)
-            .map(|(_, file_index_set)| {
-                file_index_set
-                    .iter()
-                    .map(|file_index| self.get_file_path_by_index(*file_index))
-                    .collect()
-            })
-            .collect())
+    /// Report the progress of `update` to `observer`, e.g. a `BarObserver` drawn in the
+    /// `MultiProgress` of a larger tool. Nothing reports it by default.
+    pub fn with_progress_observer(mut self, observer: impl ProgressObserver + 'static) -> Self {
+        self.progress_observer = Box::new(observer);
+        self
     }
 
-    fn duplicates_strict(&self, shallow: bool) -> Result<Vec<Vec<&Path>>> {
-        let dups = self.duplicates_common()?;
-        let mut diff_files: Vec<Vec<&Path>> = Vec::new();
-        for dup in dups {
-            for file in dup {
-                for same_files in &mut diff_files {
-                    let first_file = same_files[0];
-                    if file_cmp(file, first_file, shallow)? {
-                        same_files.push(file);
-                        break;
-                    }
-                }
-                diff_files.push(vec![file]);
-            }
-        }
+    /// Draw the progress of `update` as progress bars on stderr if `show_progress`, or
+    /// report it nowhere, the default. This replaces the progress observer.
+    pub fn set_progress(&mut self, show_progress: bool) -> &mut Self {
+        self.progress_observer = if show_progress {
+            Box::new(BarObserver::new())
+        } else {
+            Box::new(NoopObserver)
+        };
+        self
+    }
 
-        Ok(diff_files)
+    /// Seed the xxhash hasher with `seed` instead of 0, e.g. to guard against crafted
+    /// collisions or to match an external tool. This replaces the hasher creator, a cache
+    /// file saved with another seed is discarded by `load_cache`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.hasher_creator = Box::new(move || Box::new(XxHash64::with_seed(seed)));
+        self
     }
 
-    fn duplicates_symlink(&self) -> Vec<Vec<&Path>> {
-        self.symlink_hash_dict
-            .iter()
-            .filter(|(_, v)| v.len() > 1)
-            .map(|(_, symlink_set)| symlink_set.iter().map(|p| p.as_ref()).collect())
-            .collect()
+    /// Compute the full hashes with `algorithm` instead of the hasher creator. With a
+    /// cryptographic digest, `StrictLevel::Common` can be trusted without byte comparison.
+    pub fn with_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
     }
 
-    fn update_directory(&mut self, dir: impl AsRef<Path>) -> Result<HashSet<FileIndex>> {
-        let mut entries = Vec::new();
-        for entry in WalkDir::new(dir).follow_links(self.follow_links) {
-            let entry = match entry {
-                Ok(val) => val,
-                Err(e) if self.ignore_error => {
-                    if let Some(path) = e.path() {
-                        self.ignored_files.push(path.to_owned());
-                    }
-                    continue;
-                }
-                Err(e) => return Err(walkdir_error!(e)),
-            };
+    /// Compute the small hash on the first `size` bytes of the files instead of 1024, so
+    /// the files sharing a long header (same container format, same preamble...) are told
+    /// apart before their full hash. A cache saved with another size is discarded.
+    pub fn with_small_hash_size(mut self, size: usize) -> Self {
+        self.small_hash_size = size;
+        self
+    }
 
-            if !self.ignore_symlink && entry.path_is_symlink() {
-                // deal with symlink
-                match self.update_symlink(&entry) {
-                    Ok(()) => {}
-                    Err(_e) if self.ignore_error => {
-                        self.ignored_files.push(entry.path().to_owned());
-                        continue;
-                    }
-                    Err(e) => return Err(io_error!(e)),
-                };
-            } else if entry.file_type().is_file() {
-                // deal with regular file
-                entries.push(entry);
-            }
-        }
-        // Processing symlinks separately, so all the files in entries are regular file
-        self.update_regular_files(entries)
+    /// Read the files by chunks of `size` bytes (at least 1) instead of 128 KiB while
+    /// computing their full hashes. Larger buffers mean fewer system calls on big files, the
+    /// files smaller than the buffer only get a buffer of their size.
+    pub fn with_read_buffer_size(mut self, size: usize) -> Self {
+        self.read_buffer_size = size.max(1);
+        self
     }
 
-    /// Processing symbolic links separately
-    fn update_symlink(&mut self, symlink: &DirEntry) -> io::Result<()> {
-        let key = fs::read_link(symlink.path())?;
-        self.symlink_hash_dict
-            .entry(key)
-            .or_insert_with(|| HashSet::new())
-            .insert(symlink.path().to_owned());
-        Ok(())
+    /// Only hash the first `limit` bytes of the files in the full hash pass, for a quick
+    /// triage of a large archive. The duplicates found are only candidates sharing their
+    /// first bytes, to be confirmed e.g. by a scan without limit or with
+    /// `StrictLevel::ByteByByte`, see `reports_candidates`. A cache saved with another limit
+    /// is discarded.
+    pub fn with_full_hash_limit(mut self, limit: u64) -> Self {
+        self.full_hash_limit = Some(limit);
+        self
     }
 
-    fn update_regular_files<T>(&mut self, entries: T) -> Result<HashSet<FileIndex>>
-    where
-        T: IntoIterator<Item = DirEntry>,
-    {
-        let mut size_dict_temp: SizeDict = HashMap::new();
-        let mut small_hash_dict_temp: SmallHashDict = HashMap::new();
-        let mut full_hash_dict_temp: FullHashDict = HashMap::new();
-        let mut duplicate_files_index: HashSet<FileIndex> = HashSet::new();
+    /// Treat the paths differing only in case as the same file, for the case-insensitive
+    /// filesystems (the defaults on Windows and macOS), so a file reached through
+    /// differently cased paths is indexed once instead of being reported as a duplicate of
+    /// itself. The first path met is the one reported.
+    ///
+    /// With `with_canonicalize`, the canonical path usually already has the case stored on
+    /// disk, this also covers the systems where it doesn't. Paths which are not valid
+    /// Unicode are compared as they are.
+    pub fn with_case_insensitive_paths(mut self, case_insensitive_paths: bool) -> Self {
+        self.case_insensitive_paths = case_insensitive_paths;
+        self
+    }
 
-        for entry in entries.into_iter().progress() {
-            let path: &Path = entry.path();
-            let file_size = entry.metadata().map_err(|e| walkdir_error!(e))?.len() as FileSize;
-            let file_index = self.add_file_info(path, file_size, None, None);
-            size_dict_temp
-                .entry(file_size)
-                .or_insert_with(|| HashSet::new())
-                .insert(file_index);
-        }
+    /// Stop `update` with `JustOneError::Cancelled` as soon as `cancel_token` is set, e.g.
+    /// from a Stop button or a SIGINT handler. It is checked once per file in each pass.
+    ///
+    /// The files indexed and hashed until then stay in the index, so the duplicates found
+    /// so far can still be reported, and updating the same folder again completes it.
+    pub fn with_cancel_token(mut self, cancel_token: Arc<AtomicBool>) -> Self {
+        self.cancel_token = Some(cancel_token);
+        self
+    }
 
-        for (file_size, file_index) in self.merge_size_dict(size_dict_temp).into_iter().progress() {
-            let small_hash = match self.get_small_hash(file_index) {
-                Ok(val) => val,
-                Err(_) if self.ignore_error => {
-                    self.ignored_files
-                        .push(self.file_info.get(file_index).unwrap().path.clone());
-                    continue;
-                }
-                Err(e) => return Err(e),
-            };
-            let key = (file_size, small_hash);
-            small_hash_dict_temp
-                .entry(key)
-                .or_insert_with(|| HashSet::new())
-                .insert(file_index);
-        }
+    /// Compute the small and full hashes on `threads` threads. With 0 or 1 thread (the
+    /// default), files are hashed serially on the calling thread.
+    ///
+    /// If the thread pool can't be created, hashing falls back to serial.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.thread_pool = if threads > 1 {
+            ThreadPoolBuilder::new().num_threads(threads).build().ok()
+        } else {
+            None
+        };
+        self
+    }
 
-        for file_index in self
-            .merge_small_hash_dict(small_hash_dict_temp)
-            .into_iter()
-            .progress()
-        {
-            let full_hash = match self.get_full_hash(file_index) {
-                Ok(val) => val,
-                Err(_) if self.ignore_error => {
-                    self.ignored_files
-                        .push(self.file_info.get(file_index).unwrap().path.clone());
-                    continue;
-                }
-                Err(e) => return Err(e),
-            };
-            full_hash_dict_temp
-                .entry(full_hash)
-                .or_insert_with(|| HashSet::new())
-                .insert(file_index);
-        }
+    /// Index files by their canonical path, so scanning overlapping folders never reports a
+    /// file as its own duplicate. It costs a syscall per file, and the canonical paths are
+    /// the ones reported. Symlinks keep their own path and are still grouped by their content.
+    pub fn with_canonicalize(mut self, canonicalize: bool) -> Self {
+        self.canonicalize = canonicalize;
+        self
+    }
 
-        for file_index in self
-            .merge_full_hash_dict(full_hash_dict_temp)
-            .into_iter()
-            .progress()
-        {
-            duplicate_files_index.insert(file_index);
-        }
+    /// Choose whether the symlinks are skipped, compared as links or traversed
+    pub fn with_symlink_policy(mut self, symlink_policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = symlink_policy;
+        self
+    }
 
-        Ok(duplicate_files_index)
+    /// Group the symlinks by the canonical path of their target, so `a -> alias/x` and
+    /// `b -> real/x` are duplicates when `alias` links to `real`, instead of the target
+    /// resolved lexically from the folder of the link. A broken symlink is still grouped
+    /// by its lexical target.
+    pub fn with_symlink_resolve(mut self, symlink_resolve: bool) -> Self {
+        self.symlink_resolve = symlink_resolve;
+        self
     }
 
-    fn add_file_info(
-        &mut self,
-        path: &Path,
-        file_size: FileSize,
-        small_hash: Option<SmallHash>,
-        full_hash: Option<FullHash>,
-    ) -> FileIndex {
-        self.file_index.get(path).copied().unwrap_or_else(|| {
-            let index = self.file_info.len();
-            let old_index = self.file_index.insert(path.into(), index);
-            debug_assert_eq!(old_index, None);
-            self.file_info.push(FileInfo {
-                id: index,
-                path: path.into(),
-                size: file_size as FileSize,
-                small_hash,
-                full_hash,
-            });
-            index
-        })
+    /// Don't descend into the folders on another filesystem than the scanned folder, like
+    /// `find -xdev`, e.g. the mount points below `/`. Filesystems are told apart by device
+    /// on Unix and by volume on Windows.
+    pub fn with_same_filesystem(mut self, same_filesystem: bool) -> Self {
+        self.same_filesystem = same_filesystem;
+        self
     }
 
-    fn get_file_path_by_index(&self, file_index: FileIndex) -> &Path {
-        &self.file_info.get(file_index).unwrap().path
+    /// Skip the files and folders ignored by the `.gitignore` and `.ignore` files met during
+    /// the traversal, the way ripgrep does. Each ignore file applies to the folder holding
+    /// it and below, the deeper ones taking precedence. The ignore files above the scanned
+    /// folder and the global git excludes are not read.
+    pub fn with_respect_gitignore(mut self, respect_gitignore: bool) -> Self {
+        self.respect_gitignore = respect_gitignore;
+        self
     }
 
-    fn merge_size_dict(&mut self, size_dict_temp: SizeDict) -> Vec<(FileSize, FileIndex)> {
-        let mut merged: Vec<(FileSize, FileIndex)> = Vec::new();
-        for (file_size, file_index_set_temp) in size_dict_temp {
-            self.size_dict
-                .entry(file_size)
-                .or_insert_with(|| HashSet::new());
-            let file_index_set = self.size_dict.get_mut(&file_size).unwrap();
-            let is_single = file_index_set.len() == 1;
-            file_index_set.extend(file_index_set_temp.iter());
-            if file_index_set.len() > 1 {
-                let set = if is_single {
-                    &*file_index_set
-                } else {
-                    &file_index_set_temp
-                };
-                merged.extend(set.iter().map(|&file_index| (file_size, file_index)));
-            }
-        }
-        merged
+    /// Only index the regular files with one of these extensions, case-insensitively, e.g.
+    /// `jpg` or `.png`. Files without an extension are skipped once a filter is set.
+    pub fn with_extensions(mut self, exts: Vec<String>) -> Self {
+        self.extensions = Some(
+            exts.iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+        );
+        self
     }
 
-    fn merge_small_hash_dict(&mut self, small_hash_dict_temp: SmallHashDict) -> Vec<FileIndex> {
-        let mut merged: Vec<FileIndex> = Vec::new();
-        for (file_size_and_small_hash, file_index_set_temp) in small_hash_dict_temp {
-            self.small_hash_dict
-                .entry(file_size_and_small_hash)
-                .or_insert_with(|| HashSet::new());
-            let file_index_set = self
-                .small_hash_dict
-                .get_mut(&file_size_and_small_hash)
-                .unwrap();
-            let is_single = file_index_set.len() == 1;
-            file_index_set.extend(file_index_set_temp.iter());
-            if file_index_set.len() > 1 {
-                let set = if is_single {
-                    &*file_index_set
-                } else {
-                    &file_index_set_temp
-                };
-                merged.extend(set.iter());
-            }
-        }
-        merged
+    /// Only traverse folders down to `depth` levels, with the same semantics as walkdir:
+    /// depth 0 is the folder itself, depth 1 its direct children, and so on.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
     }
 
-    fn merge_full_hash_dict(&mut self, full_hash_dict_temp: FullHashDict) -> Vec<FileIndex> {
-        let mut merged: Vec<FileIndex> = Vec::new();
-        for (full_hash, file_index_set_temp) in full_hash_dict_temp {
-            self.full_hash_dict
-                .entry(full_hash)
-                .or_insert_with(|| HashSet::new());
-            let file_index_set = self.full_hash_dict.get_mut(&full_hash).unwrap();
-            let is_single = file_index_set.len() == 1;
-            file_index_set.extend(file_index_set_temp.iter());
-            if file_index_set.len() > 1 {
-                let set = if is_single {
-                    &*file_index_set
-                } else {
-                    &file_index_set_temp
-                };
-                merged.extend(set.iter());
-            }
-        }
-        merged
+    /// Skip the entries shallower than `depth` levels, e.g. depth 2 skips the files directly
+    /// in the folder.
+    pub fn with_min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = Some(depth);
+        self
     }
 
-    fn get_small_hash(&mut self, file_index: FileIndex) -> Result<SmallHash> {
-        let mut file_info = self.file_info.get_mut(file_index).unwrap();
+    /// Compute the full hashes on the files mapped in memory instead of reading them through
+    /// a buffer, which is faster for large files. Empty files, special files and files too
+    /// large for the address space are still read through a buffer.
+    ///
+    /// A mapped file truncated by another process while it is hashed makes the whole
+    /// process crash (SIGBUS on Unix), so only turn it on for files nobody else writes to.
+    pub fn with_mmap(mut self, mmap: bool) -> Self {
+        self.mmap = mmap;
+        self
+    }
 
-        if let Some(hash) = file_info.small_hash {
-            Ok(hash)
-        } else {
-            let path = &file_info.path;
-            let mut f = File::open(path).map_err(|e| io_error!(e, path))?;
-            let hasher_creator = self.hasher_creator.as_ref();
-            let hasher = hasher_creator();
-            let hash = get_small_hash(&mut f, hasher).map_err(|e| io_error!(e, path))?;
-            file_info.small_hash = Some(hash);
-            Ok(hash)
-        }
+    /// Compare the hardlinks to a same file like separate files, so they are hashed each and
+    /// reported as duplicates of each other, instead of being grouped by `hardlink_groups`.
+    /// Deleting such a duplicate frees no space.
+    pub fn with_report_hardlinks(mut self, report_hardlinks: bool) -> Self {
+        self.report_hardlinks = report_hardlinks;
+        self
     }
 
-    fn get_full_hash(&mut self, file_index: FileIndex) -> Result<FullHash> {
-        let mut file_info = self.file_info.get_mut(file_index).unwrap();
+    /// Skip the zero-byte files, which all share the same hash and would otherwise make up
+    /// one large duplicate group. They are not returned by `empty_files` either.
+    pub fn with_ignore_empty(mut self, ignore_empty: bool) -> Self {
+        self.ignore_empty = ignore_empty;
+        self
+    }
 
-        if let Some(hash) = file_info.full_hash {
-            Ok(hash)
-        } else {
-            let path = &file_info.path;
-            let mut f = File::open(path).map_err(|e| io_error!(e, path))?;
-            let hasher_creator = self.hasher_creator.as_ref();
-            let hasher = hasher_creator();
-            let hash = get_full_hash(&mut f, hasher).map_err(|e| io_error!(e, path))?;
-            file_info.full_hash = Some(hash);
-            Ok(hash)
-        }
+    /// Skip the regular files smaller than `size` bytes, e.g. the many tiny files of an
+    /// archive which are rarely interesting duplicates. They are not indexed at all, so they
+    /// are not returned by `empty_files` or `uniques` either.
+    pub fn with_min_size(mut self, size: FileSize) -> Self {
+        self.min_size = Some(size);
+        self
     }
-}
 
-fn get_small_hash(f: &mut dyn io::Read, mut hasher: Box<dyn Hasher>) -> io::Result<SmallHash> {
-    let mut buffer = [0; SMALL_HASH_CHUNK_SIZE];
-    let read_size = f.read(&mut buffer)?;
-    hasher.write(&buffer[..read_size]);
-    Ok(SmallHash(hasher.finish()))
-}
+    /// Skip the regular files larger than `size` bytes, e.g. disk images already known to be
+    /// unique and too slow to hash. Unlike the small files of `with_min_size`, they are
+    /// listed by `too_large` so they can be reported.
+    pub fn with_max_size(mut self, size: FileSize) -> Self {
+        self.max_size = Some(size);
+        self
+    }
 
-fn get_full_hash(f: &mut dyn io::Read, mut hasher: Box<dyn Hasher>) -> io::Result<FullHash> {
-    let mut buffer = [0; FILE_READ_BUFFER_SIZE];
-    loop {
-        let read_size = f.read(&mut buffer)?;
-        if read_size == 0 {
-            break;
-        }
-        hasher.write(&buffer[..read_size]);
+    /// Only report files with the same file name (e.g. a photo copied under its original
+    /// name) as duplicates, the files of each group being split by name. Symlinks are not
+    /// affected.
+    pub fn with_require_same_name(mut self, require_same_name: bool) -> Self {
+        self.require_same_name = require_same_name;
+        self
     }
-    Ok(FullHash(hasher.finish()))
-}
 
-fn file_cmp(file_a: impl AsRef<Path>, file_b: impl AsRef<Path>, shallow: bool) -> Result<bool> {
-    Ok(filecmp::cmp(&file_a, &file_b, shallow).map_err(|e| io_error!(e, file_a, file_b))?)
-}
+    /// Drop the files whose size is unique right after the size pass, freeing their paths
+    /// before hashing starts, since they can never be duplicates. The files with several
+    /// hardlinks are kept, since they make up a hardlink group.
+    ///
+    /// The dropped files are no longer queryable afterward (e.g. by `empty_files` or
+    /// `tree_fingerprint`), and a same-size file found by a later `update` can't be
+    /// matched against them.
+    pub fn with_low_memory(mut self, low_memory: bool) -> Self {
+        self.low_memory = low_memory;
+        self
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Force (`true`) or disable (`false`) hashing the whole files sharing their size with
+    /// another one right away, in the pass of the small hash, instead of comparing their
+    /// small hash first. By default, it is only done for the files no larger than the small
+    /// hash size, which the small hash reads whole anyway, saving them a second open.
+    ///
+    /// The small hash saves reading the files of a same size which differ early, which
+    /// outweighs the second open as soon as the files are larger than the small hash: with
+    /// half of the same-size files differing, skipping it was already slower at 2 KiB on a
+    /// warm cache, whatever the number of files sharing a size.
+    pub fn with_skip_small_hash(mut self, skip_small_hash: bool) -> Self {
+        self.skip_small_hash = Some(skip_small_hash);
+        self
+    }
 
-    #[test]
-    fn test_get_small_hash() {
-        let hasher_creator = default_hasher_creator();
+    /// Return true if the full hashes only cover the start of the files (see
+    /// `with_full_hash_limit`), so the duplicates still require confirmation
+    pub fn reports_candidates(&self) -> bool {
+        self.full_hash_limit.is_some()
+    }
+
+    pub fn update(&mut self, dir: impl AsRef<Path>) -> Result<&mut Self> {
+        self.update_directory(dir)?;
+
+        Ok(self)
+    }
+
+    /// Same as calling `update` on each folder, but the folders are traversed concurrently,
+    /// one thread each, which overlaps the IO latency of folders on separate disks or
+    /// network mounts. The files are indexed as they are found and then hashed together.
+    ///
+    /// If a folder fails, nothing is hashed and the first error is returned.
+    pub fn update_all<P: AsRef<Path> + Sync>(&mut self, dirs: &[P]) -> Result<&mut Self> {
+        let roots: Vec<RootIndex> = dirs
+            .iter()
+            .map(|dir| self.add_root(&self.index_path(dir.as_ref())))
+            .collect();
+        let walks: Vec<_> = dirs
+            .iter()
+            .map(|dir| self.walk_path(dir.as_ref(), dir.as_ref()))
+            .collect();
+
+        let mut size_dict_temp: SizeDict = HashMap::new();
+        self.start_phase(Phase::Size, 0);
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::sync_channel(WALK_CHANNEL_CAPACITY);
+            let handles: Vec<_> = walks
+                .into_iter()
+                .zip(roots)
+                .map(|(walk, root)| {
+                    let sender = sender.clone();
+                    scope.spawn(move || {
+                        for found in walk {
+                            let is_err = found.is_err();
+                            // the receiver is gone once an error stopped the indexing
+                            if sender.send((found, root)).is_err() || is_err {
+                                break;
+                            }
+                        }
+                    })
+                })
+                .collect();
+            drop(sender);
+            let indexed = receiver
+                .into_iter()
+                .try_for_each(|(found, root)| self.index_found(found?, root, &mut size_dict_temp));
+            for handle in handles {
+                handle.join().unwrap_or_else(|e| panic::resume_unwind(e));
+            }
+            indexed
+        })?;
+        self.progress_observer.on_phase_end(Phase::Size);
+        self.update_regular_files(size_dict_temp)?;
+
+        Ok(self)
+    }
+
+    /// Index and compare the given files like `update` does with the files of a folder, e.g.
+    /// a list from `find` or a database, without any traversal. The excludes, extensions
+    /// and depth limits don't apply, the symlinks are followed, and each file is indexed
+    /// under its folder as the root.
+    ///
+    /// A path which doesn't exist or isn't a regular file is an error, or a skipped file
+    /// unless the error policy is `ErrorPolicy::FailFast`.
+    pub fn update_files(&mut self, paths: impl IntoIterator<Item = PathBuf>) -> Result<&mut Self> {
+        let mut size_dict_temp: SizeDict = HashMap::new();
+        self.start_phase(Phase::Size, 0);
+        for path in paths {
+            self.check_cancelled()?;
+            self.progress.files_walked.fetch_add(1, Ordering::Relaxed);
+            let root_dir = path.parent().unwrap_or(&path);
+            let root = self.add_file_root(&self.index_path(root_dir));
+            let metadata = fs::metadata(&path).and_then(|metadata| {
+                if metadata.is_file() {
+                    Ok(metadata)
+                } else {
+                    let message = "not a regular file";
+                    Err(io::Error::new(io::ErrorKind::InvalidInput, message))
+                }
+            });
+            let found = match metadata {
+                Ok(metadata) => Found::file(path, &metadata),
+                Err(e) if self.skips_errors() => {
+                    let error = io_error!(e, path);
+                    Found::Ignored(path, error)
+                }
+                Err(e) => return Err(io_error!(e, path)),
+            };
+            self.index_found(found, root, &mut size_dict_temp)?;
+        }
+        self.progress_observer.on_phase_end(Phase::Size);
+        self.update_regular_files(size_dict_temp)?;
+
+        Ok(self)
+    }
+
+    /// Index and compare a single file like `update_files` does, with its folder as the root
+    pub fn update_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self> {
+        self.update_files(std::iter::once(path.as_ref().to_path_buf()))
+    }
+
+    /// Add the files, symlinks and skipped files of `other`, e.g. an index built by another
+    /// thread or machine over another folder, then compare them with the files of this
+    /// index like `update` does, so the duplicates spanning both are found. The hashes
+    /// already computed by either index are reused, the other files sharing their size with
+    /// a file of the other index are read, so their paths must be reachable from here.
+    ///
+    /// Return `JustOneError::IncompatibleIndex` if the indexes don't hash the same way: with
+    /// another algorithm, small hash size, full hash limit, or hasher (e.g. another seed).
+    pub fn merge(&mut self, other: JustOne) -> Result<&mut Self> {
+        if self.hash_algorithm != other.hash_algorithm {
+            return Err(JustOneError::IncompatibleIndex("hash algorithms"));
+        }
+        if self.small_hash_size != other.small_hash_size {
+            return Err(JustOneError::IncompatibleIndex("small hash sizes"));
+        }
+        if self.full_hash_limit != other.full_hash_limit {
+            return Err(JustOneError::IncompatibleIndex("full hash limits"));
+        }
+        if self.hasher_fingerprint() != other.hasher_fingerprint() {
+            return Err(JustOneError::IncompatibleIndex("hashers"));
+        }
+        if self.normalize_text != other.normalize_text {
+            return Err(JustOneError::IncompatibleIndex("text normalizations"));
+        }
+
+        let roots: Vec<RootIndex> = other
+            .roots
+            .iter()
+            .enumerate()
+            .map(|(root, root_dir)| {
+                if other.file_roots.contains(&root) {
+                    self.add_file_root(root_dir)
+                } else {
+                    self.add_root(root_dir)
+                }
+            })
+            .collect();
+        let mut new_indexes: HashMap<FileIndex, FileIndex> = HashMap::new();
+        let mut new_files: Vec<(FileSize, FileIndex)> = Vec::new();
+        for (other_index, file_info) in other.file_info.into_iter().enumerate() {
+            let file_info = match file_info {
+                Some(file_info) => file_info,
+                None => continue,
+            };
+            let is_new = self.lookup_file_index(&file_info.path).is_none();
+            let times = FileTimes {
+                modified: file_info.modified,
+                created: file_info.created,
+            };
+            let file_index = self.add_file_info(
+                &file_info.path,
+                roots[file_info.root],
+                file_info.size,
+                times,
+                file_info.small_hash,
+                file_info.full_hash,
+            );
+            new_indexes.insert(other_index, file_index);
+            if is_new {
+                new_files.push((self.size_key(file_info.size), file_index));
+            }
+        }
+
+        // only the first path of each hardlink list stands for the file in the dicts
+        let mut secondary_links = HashSet::new();
+        for (file_id, links) in other.hardlink_dict {
+            let links: Vec<FileIndex> = links
+                .iter()
+                .filter_map(|file_index| new_indexes.get(file_index).copied())
+                .collect();
+            if links.is_empty() {
+                continue;
+            }
+            for &file_index in &links {
+                self.get_file_info_mut(file_index).file_id = Some(file_id);
+            }
+            let merged_links = self.hardlink_dict.entry(file_id).or_default();
+            for file_index in links {
+                if !merged_links.contains(&file_index) {
+                    merged_links.push(file_index);
+                }
+            }
+            secondary_links.extend(merged_links[1..].iter().copied());
+        }
+        let mut size_dict_temp: SizeDict = HashMap::new();
+        for (file_size, file_index) in new_files {
+            if !secondary_links.contains(&file_index) {
+                size_dict_temp
+                    .entry(file_size)
+                    .or_default()
+                    .insert(file_index);
+            }
+        }
+
+        for (target, paths) in other.symlink_hash_dict {
+            self.symlink_hash_dict
+                .entry(target)
+                .or_default()
+                .extend(paths);
+        }
+        self.ignored_files.extend(other.ignored_files);
+        self.errors.extend(other.errors);
+        for path in other.too_large_files {
+            self.add_too_large(path);
+        }
+
+        self.update_regular_files(size_dict_temp)?;
+        Ok(self)
+    }
+
+    /// Empty the index, the skipped files and the statistics, so the next `update` starts
+    /// a scan independent of the previous ones. The configuration is kept, along with the
+    /// read-only roots and the hashes loaded by `load_cache`.
+    pub fn reset(&mut self) -> &mut Self {
+        self.ignored_files.clear();
+        self.errors.clear();
+        self.too_large_files.clear();
+        self.too_large_paths.clear();
+        self.roots.clear();
+        self.file_roots.clear();
+        self.file_info.clear();
+        self.file_index.clear();
+        self.size_dict.clear();
+        self.small_hash_dict.clear();
+        self.full_hash_dict.clear();
+        self.symlink_hash_dict.clear();
+        self.hardlink_dict.clear();
+        self.progress.reset();
+        self
+    }
+
+    /// Drop every file and symlink under `dir` from the index, as if it had never been
+    /// scanned, and return how many were dropped. The folder doesn't need to exist anymore.
+    pub fn remove_directory(&mut self, dir: impl AsRef<Path>) -> usize {
+        let dir = self.index_path(dir.as_ref()).into_owned();
+        let file_indexes: Vec<FileIndex> = self
+            .file_index
+            .iter()
+            .filter(|(path, _)| path.starts_with(&dir))
+            .map(|(_, &file_index)| file_index)
+            .collect();
+        for &file_index in &file_indexes {
+            self.remove_file_index(file_index);
+        }
+        file_indexes.len() + self.remove_symlinks(|path| path.starts_with(&dir))
+    }
+
+    /// Drop the file or symlink at `path` from the index, returning false if it wasn't
+    /// indexed. The file doesn't need to exist anymore.
+    pub fn remove_file(&mut self, path: impl AsRef<Path>) -> bool {
+        // only the parent is canonicalized, the file may be gone already
+        let path = self.index_symlink_path(path.as_ref()).into_owned();
+        match self.lookup_file_index(&path) {
+            Some(file_index) => {
+                self.remove_file_index(file_index);
+                true
+            }
+            None => self.remove_symlinks(|symlink| symlink == path) > 0,
+        }
+    }
+
+    /// Drop the files whose size is unique in the index, which can't be duplicates, to free
+    /// their memory in a long-running process, and return how many were dropped. The files
+    /// with several hardlinks are kept, since they make up a hardlink group.
+    ///
+    /// A pruned file is not matched against the same-size files found by later `update`
+    /// calls, its folder must be scanned again to add it back. See `with_low_memory` to
+    /// prune after every scan.
+    pub fn prune_singletons(&mut self) -> usize {
+        let linked = self.linked_files();
+        let file_indexes: Vec<FileIndex> = self
+            .size_dict
+            .values()
+            .filter(|file_index_set| file_index_set.len() == 1)
+            .flatten()
+            .filter(|file_index| !linked.contains(file_index))
+            .copied()
+            .collect();
+        for &file_index in &file_indexes {
+            self.remove_file_index(file_index);
+        }
+        file_indexes.len()
+    }
+
+    /// Protect every file under `dir`: actions refuse to delete or modify them, whatever
+    /// file they would keep otherwise.
+    pub fn mark_readonly_root(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        let dir = dir.as_ref();
+        self.readonly_roots
+            .push(fs::canonicalize(dir).unwrap_or_else(|_| dir.to_owned()));
+        self
+    }
+
+    /// Return true if `path` is under a root marked by `mark_readonly_root`
+    pub fn is_readonly(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        let canonical_path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        self.readonly_roots
+            .iter()
+            .any(|root| path.starts_with(root) || canonical_path.starts_with(root))
+    }
+
+    /// Return the counters of the work done so far, which also tells how far a cancelled or
+    /// failed scan went.
+    pub fn progress_snapshot(&self) -> ProgressSnapshot {
+        self.progress.snapshot()
+    }
+
+    /// Return how much work the scans did so far, cumulated over all the `update` calls
+    pub fn stats(&self) -> Stats {
+        self.progress.stats()
+    }
+
+    /// Return a handle to query `progress_snapshot` from another thread during `update`
+    pub fn progress_handle(&self) -> ProgressHandle {
+        ProgressHandle(Arc::clone(&self.progress))
+    }
+
+    pub fn duplicates(&self) -> Result<Vec<Vec<&Path>>> {
+        self.duplicate_groups().collect()
+    }
+
+    /// Same as `duplicates`, with owned paths which don't borrow the index, e.g. to keep the
+    /// result after the `JustOne` is dropped or send it to another thread.
+    pub fn duplicates_owned(&self) -> Result<Vec<Vec<PathBuf>>> {
+        self.duplicate_groups()
+            .map(|group| group.map(|group| group.into_iter().map(Path::to_owned).collect()))
+            .collect()
+    }
+
+    /// Return the duplicate groups in a stable order, so the output can be diffed between
+    /// runs: the paths of each group are sorted, then the groups by their first path.
+    pub fn duplicates_sorted(&self) -> Result<Vec<Vec<&Path>>> {
+        let mut dups = self.duplicates()?;
+        for dup in &mut dups {
+            dup.sort_unstable();
+        }
+        dups.sort_unstable();
+        Ok(dups)
+    }
+
+    /// Yield the duplicate groups one by one, the groups of symlinks coming last.
+    ///
+    /// With `StrictLevel::Common` the groups are built lazily from the index, the stricter
+    /// levels compare all the files before yielding the first group.
+    pub fn duplicate_groups(&self) -> impl Iterator<Item = Result<Vec<&Path>>> {
+        let duplicate_files = self.duplicate_file_groups();
+        let duplicate_symlinks = if self.reports_symlinks() {
+            Some(self.duplicates_symlink().map(Ok))
+        } else {
+            None
+        };
+        duplicate_files.chain(duplicate_symlinks.into_iter().flatten())
+    }
+
+    /// Call `f` with each duplicate group as `duplicate_groups` yields it, so the groups can
+    /// be written out without collecting them all, e.g. on a huge filesystem.
+    pub fn for_each_duplicate_group<'a, F: FnMut(&[&'a Path])>(&'a self, mut f: F) -> Result<()> {
+        for group in self.duplicate_groups() {
+            f(&group?);
+        }
+        Ok(())
+    }
+
+    /// Return the duplicate groups of regular files along with the size of their files, so
+    /// they can be sorted by wasted space. Symlinks are left out.
+    pub fn duplicates_with_size(&self) -> Result<Vec<(FileSize, Vec<&Path>)>> {
+        self.duplicate_file_groups()
+            .map(|group| group.map(|group| (self.get_file_size(group[0]), group)))
+            .collect()
+    }
+
+    /// Same as `duplicates_with_size` in a stable order, the largest files first: the paths
+    /// of each group are sorted, then the groups by descending size and by their first path.
+    /// Symlinks are left out.
+    pub fn duplicates_by_size(&self) -> Result<Vec<(FileSize, Vec<&Path>)>> {
+        let mut dups = self.duplicates_with_size()?;
+        for (_, dup) in &mut dups {
+            dup.sort_unstable();
+        }
+        dups.sort_unstable_by(|(size_a, dup_a), (size_b, dup_b)| {
+            size_b.cmp(size_a).then_with(|| dup_a.cmp(dup_b))
+        });
+        Ok(dups)
+    }
+
+    /// Return the indexed files which `path` duplicates, without adding it to the index, e.g.
+    /// to check a file before saving it. It goes through the same size, small hash and full
+    /// hash comparison (and byte comparison with the stricter levels), and never returns
+    /// `path` itself.
+    ///
+    /// With `with_low_memory`, the files whose size was unique are not matched.
+    pub fn find_duplicates_of(&self, path: impl AsRef<Path>) -> Result<Vec<&Path>> {
+        let path = path.as_ref();
+        let metadata = fs::metadata(path).map_err(|e| io_error!(e, path))?;
+        let size = metadata.len() as FileSize;
+        let candidates = match self.size_dict.get(&self.size_key(size)) {
+            Some(candidates) => candidates,
+            None => return Ok(Vec::new()),
+        };
+        let own_index = self.lookup_file_index(&self.index_path(path));
+        let probe = FileInfo {
+            path: path.to_owned(),
+            root: 0,
+            size,
+            modified: None,
+            created: None,
+            small_hash: None,
+            full_hash: None,
+            file_id: None,
+        };
+        let small_hash = self.small_hash_of(&probe)?;
+        let mut full_hash = None;
+        let mut duplicates = Vec::new();
+        for &file_index in candidates {
+            if Some(file_index) == own_index {
+                continue;
+            }
+            let file_info = self.get_file_info(file_index);
+            if self.require_same_name
+                && (path.file_name().is_none() || file_info.path.file_name() != path.file_name())
+            {
+                continue;
+            }
+            if self.small_hash_of(file_info)? != small_hash {
+                continue;
+            }
+            let full_hash = match &full_hash {
+                Some(full_hash) => full_hash,
+                None => full_hash.insert(self.full_hash_of(&probe)?),
+            };
+            if self.full_hash_of(file_info)? != *full_hash {
+                continue;
+            }
+            let is_same = match self.strict_level {
+                StrictLevel::Common => true,
+                StrictLevel::Shallow => file_cmp(path, &file_info.path, true)?,
+                StrictLevel::CommonVerified | StrictLevel::ByteByByte => {
+                    file_cmp(path, &file_info.path, false)?
+                }
+            };
+            if is_same {
+                duplicates.push(file_info.path.as_ref());
+            }
+        }
+        duplicates.sort_unstable();
+        Ok(duplicates)
+    }
+
+    /// Return how many bytes could be freed by keeping only one file of each duplicate group
+    pub fn wasted_bytes(&self) -> Result<u64> {
+        Ok(self
+            .duplicates_with_size()?
+            .iter()
+            .map(|(size, group)| *size as u64 * (group.len() as u64 - 1))
+            .sum())
+    }
+
+    /// Return the number of groups `duplicates` would return, symlink groups included.
+    ///
+    /// With `StrictLevel::Common` and without `with_require_same_name`, it is counted from the
+    /// index without collecting any path.
+    pub fn group_count(&self) -> Result<usize> {
+        let mut count = 0;
+        self.for_each_group_len(|_| count += 1)?;
+        Ok(count)
+    }
+
+    /// Return the number of redundant files of the groups `duplicates` would return, the
+    /// files beyond the first of each group, counted like `group_count`
+    pub fn duplicate_count(&self) -> Result<usize> {
+        let mut count = 0;
+        self.for_each_group_len(|len| count += len - 1)?;
+        Ok(count)
+    }
+
+    /// Call `f` with the number of paths of each group `duplicates` would return
+    fn for_each_group_len(&self, mut f: impl FnMut(usize)) -> Result<()> {
+        if matches!(self.strict_level, StrictLevel::Common) && !self.require_same_name {
+            self.full_hash_dict
+                .values()
+                .filter(|file_index_set| file_index_set.len() > 1)
+                .for_each(|file_index_set| f(file_index_set.len()));
+        } else {
+            for group in self.duplicate_file_groups() {
+                f(group?.len());
+            }
+        }
+        if self.reports_symlinks() {
+            self.symlink_hash_dict
+                .values()
+                .filter(|symlink_set| symlink_set.len() > 1)
+                .for_each(|symlink_set| f(symlink_set.len()));
+        }
+        Ok(())
+    }
+
+    fn duplicate_file_groups(&self) -> DuplicateGroups<'_> {
+        let groups: DuplicateGroups<'_> = match self.strict_level {
+            StrictLevel::Common => Box::new(self.duplicates_common().map(Ok)),
+            StrictLevel::CommonVerified => Box::new(
+                self.duplicates_common()
+                    .flat_map(|group| eager_groups(split_identical(group, false))),
+            ),
+            StrictLevel::Shallow => eager_groups(self.duplicates_strict(true)),
+            StrictLevel::ByteByByte => eager_groups(self.duplicates_strict(false)),
+        };
+        if !self.require_same_name {
+            return groups;
+        }
+        Box::new(groups.flat_map(|group| match group {
+            Ok(group) => eager_groups(Ok(split_by_name(group))),
+            Err(e) => eager_groups(Err(e)),
+        }))
+    }
+
+    /// Return the groups of paths which are hardlinks to the same file.
+    ///
+    /// They already share their storage, so only the first path of each group is compared
+    /// with the other files and may appear in the duplicates. Always empty on non-Unix
+    /// platforms and with `with_report_hardlinks`.
+    pub fn hardlink_groups(&self) -> Vec<Vec<&Path>> {
+        self.hardlink_dict
+            .values()
+            .filter(|links| links.len() > 1)
+            .map(|links| {
+                links
+                    .iter()
+                    .map(|file_index| self.get_file_path_by_index(*file_index))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Return the files skipped because of an error so far, which only happens if the
+    /// error policy isn't `ErrorPolicy::FailFast`, or for the files `link_duplicates` can't
+    /// link across filesystems.
+    pub fn ignored(&self) -> &[IgnoredFile] {
+        &self.ignored_files
+    }
+
+    /// Return the errors of the files skipped so far with `ErrorPolicy::Collect`, in the
+    /// order they happened, each with the OS error and the paths involved. Empty with the
+    /// other policies.
+    pub fn errors(&self) -> &[JustOneError] {
+        &self.errors
+    }
+
+    /// Return the regular files skipped so far for being larger than `with_max_size`, each
+    /// listed once however many scans met it
+    pub fn too_large(&self) -> &[PathBuf] {
+        &self.too_large_files
+    }
+
+    /// Return the number of regular files in the index, symlinks left out
+    pub fn len(&self) -> usize {
+        self.file_index.len()
+    }
+
+    /// Return the total size in bytes of the regular files in the index, a file with
+    /// several hardlinks counting once per path
+    pub fn total_size(&self) -> FileSize {
+        self.file_infos().map(|file_info| file_info.size).sum()
+    }
+
+    /// Return how many regular files were indexed, the same as `len`, e.g. to show it
+    /// along with `total_size` in a summary
+    pub fn file_count(&self) -> usize {
+        self.len()
+    }
+
+    /// Return true if no regular file is indexed
+    pub fn is_empty(&self) -> bool {
+        self.file_index.is_empty()
+    }
+
+    /// Return true if the regular file `path` is indexed
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        self.lookup_file_index(&self.index_path(path.as_ref()))
+            .is_some()
+    }
+
+    /// Return the folders scanned so far, in the order they were first scanned, the parent
+    /// folders of the files standing for the files given to `update_files`
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Return the folder of `roots` an indexed file was found under, e.g. to split a
+    /// duplicate group into its copies in a backup and in the source. `None` if the file
+    /// isn't indexed.
+    pub fn root_of(&self, path: impl AsRef<Path>) -> Option<&Path> {
+        let file_index = self.lookup_file_index(&self.index_path(path.as_ref()))?;
+        let file_info = self.file_info.get(file_index)?.as_ref()?;
+        Some(&self.roots[file_info.root])
+    }
+
+    /// Return the timestamps of an indexed file as they were when it was scanned, e.g. to
+    /// sort a duplicate group by age. `None` if the file isn't indexed.
+    pub fn file_times(&self, path: impl AsRef<Path>) -> Option<FileTimes> {
+        let file_index = self.lookup_file_index(&self.index_path(path.as_ref()))?;
+        let file_info = self.file_info.get(file_index)?.as_ref()?;
+        Some(FileTimes {
+            modified: file_info.modified,
+            created: file_info.created,
+        })
+    }
+
+    /// Return all the zero-byte files indexed so far, regardless of how many there are.
+    ///
+    /// Empty files always share the same hash, so they can be reported apart from the
+    /// real-content duplicates.
+    pub fn empty_files(&self) -> Vec<&Path> {
+        self.file_infos()
+            .filter(|file_info| file_info.size == 0)
+            .map(|file_info| file_info.path.as_ref())
+            .collect()
+    }
+
+    /// Return the indexed regular files which have no duplicate, e.g. to list what a backup
+    /// is missing. Those are the files left out of every duplicate group, including the ones
+    /// told apart by their size or small hash before any full hash; the hardlinks to a same
+    /// file are never unique.
+    ///
+    /// With `with_low_memory`, the files whose size was unique are not indexed anymore, so
+    /// they are missing too.
+    pub fn uniques(&self) -> Result<Vec<&Path>> {
+        let mut duplicated: HashSet<&Path> = HashSet::new();
+        for group in self.duplicate_file_groups() {
+            duplicated.extend(group?);
+        }
+        duplicated.extend(self.hardlink_groups().into_iter().flatten());
+        Ok(self
+            .file_infos()
+            .map(|file_info| file_info.path.as_ref())
+            .filter(|path| !duplicated.contains(path))
+            .collect())
+    }
+
+    fn duplicates_common(&self) -> impl Iterator<Item = Vec<&Path>> {
+        self.full_hash_dict
+            .iter()
+            .filter(|(_, v)| v.len() > 1)
+            .map(move |(_, file_index_set)| {
+                file_index_set
+                    .iter()
+                    .map(|file_index| self.get_file_path_by_index(*file_index))
+                    .collect()
+            })
+    }
+
+    fn duplicates_strict(&self, shallow: bool) -> Result<Vec<Vec<&Path>>> {
+        let mut identical_groups = Vec::new();
+        for dup in self.duplicates_common() {
+            identical_groups.extend(split_identical(dup, shallow)?);
+        }
+        Ok(identical_groups)
+    }
+
+    /// Return true if the symlinks are indexed as links, so their groups are reported
+    fn reports_symlinks(&self) -> bool {
+        self.symlink_policy == SymlinkPolicy::Compare
+    }
+
+    fn duplicates_symlink(&self) -> impl Iterator<Item = Vec<&Path>> {
+        self.symlink_hash_dict
+            .iter()
+            .filter(|(_, v)| v.len() > 1)
+            .map(|(_, symlink_set)| symlink_set.iter().map(|p| p.as_ref()).collect())
+    }
+
+    /// Return a fingerprint of all the scanned regular files, their contents and their paths
+    /// relative to the scanned folder.
+    ///
+    /// It does not depend on the scan order, so two trees with identical layout and contents
+    /// produce the same value. Files which were never full-hashed (e.g. unique in size) are
+    /// hashed here without being cached. Symlinks are not taken into account.
+    pub fn tree_fingerprint(&self) -> Result<u64> {
+        let mut fingerprint: u64 = 0;
+        for file_info in self.file_infos() {
+            let path = &file_info.path;
+            let full_hash = match self.full_hash_of(file_info) {
+                Ok(hash) => hash,
+                Err(_) if self.skips_errors() => continue,
+                Err(e) => return Err(e),
+            };
+            let relative_path = path
+                .strip_prefix(&self.roots[file_info.root])
+                .unwrap_or(path);
+            let hasher_creator = self.hasher_creator.as_ref();
+            let mut hasher = hasher_creator();
+            for component in relative_path.components() {
+                hasher.write(component.as_os_str().to_string_lossy().as_bytes());
+                hasher.write_u8(b'/');
+            }
+            hasher.write(&full_hash.0);
+            fingerprint = fingerprint.wrapping_add(hasher.finish());
+        }
+        Ok(fingerprint)
+    }
+
+    /// Return the root of a folder scanned as a whole, adding it if needed
+    fn add_root(&mut self, dir: &Path) -> RootIndex {
+        let root = self.add_file_root(dir);
+        self.file_roots.remove(&root);
+        root
+    }
+
+    /// Return the root of the folder of a file given to `update_files`, adding it as a file
+    /// root if needed
+    fn add_file_root(&mut self, dir: &Path) -> RootIndex {
+        match self.roots.iter().position(|root| root == dir) {
+            Some(root) => root,
+            None => {
+                self.roots.push(dir.to_owned());
+                self.file_roots.insert(self.roots.len() - 1);
+                self.roots.len() - 1
+            }
+        }
+    }
+
+    fn update_directory(&mut self, dir: impl AsRef<Path>) -> Result<HashSet<FileIndex>> {
+        let dir = dir.as_ref();
+        let root = self.add_root(&self.index_path(dir));
+        self.update_path(dir, dir, root)
+    }
+
+    /// Index the files under `path` (a folder or a file), which is inside the folder
+    /// `root_dir` of the root `root`. The depth limits and the excludes are relative to
+    /// `root_dir`.
+    fn update_path(
+        &mut self,
+        path: &Path,
+        root_dir: &Path,
+        root: RootIndex,
+    ) -> Result<HashSet<FileIndex>> {
+        let mut size_dict_temp: SizeDict = HashMap::new();
+        self.start_phase(Phase::Size, 0);
+        for found in self.walk_path(path, root_dir) {
+            self.index_found(found?, root, &mut size_dict_temp)?;
+        }
+        self.progress_observer.on_phase_end(Phase::Size);
+        self.update_regular_files(size_dict_temp)
+    }
+
+    /// Return a traversal of `path`, which is inside the folder `root_dir`, yielding the
+    /// symlinks and the regular files to index. It doesn't borrow the index, so it can run
+    /// in another thread while the entries are indexed.
+    fn walk_path(
+        &self,
+        path: &Path,
+        root_dir: &Path,
+    ) -> impl Iterator<Item = Result<Found>> + Send {
+        let offset = path
+            .strip_prefix(root_dir)
+            .map_or(0, |relative_path| relative_path.components().count());
+        let excludes = self.excludes.clone();
+        let root_dir = root_dir.to_owned();
+        let follow_links = matches!(
+            self.symlink_policy,
+            SymlinkPolicy::Follow | SymlinkPolicy::FollowInternal
+        );
+        let mut walker = WalkDir::new(path)
+            .follow_links(follow_links)
+            .same_file_system(self.same_filesystem);
+        // `path` itself may already be deeper than the depth limit
+        let mut is_too_deep = false;
+        if let Some(depth) = self.max_depth {
+            match depth.checked_sub(offset) {
+                Some(depth) => walker = walker.max_depth(depth),
+                None => is_too_deep = true,
+            }
+        }
+        if let Some(depth) = self.min_depth {
+            walker = walker.min_depth(depth.saturating_sub(offset));
+        }
+        // with follow_links, a folder reached again through another symlink is skipped, so
+        // its files are not indexed twice under different paths
+        let mut visited_dirs = HashSet::new();
+        let mut gitignore = if self.respect_gitignore {
+            Some(GitignoreStack::new(&root_dir, path))
+        } else {
+            None
+        };
+        let internal_roots: Option<Vec<PathBuf>> =
+            if self.symlink_policy == SymlinkPolicy::FollowInternal {
+                let roots = self
+                    .roots
+                    .iter()
+                    .filter_map(|root| fs::canonicalize(root).ok());
+                Some(roots.collect())
+            } else {
+                None
+            };
+        let walker = walker.into_iter().filter_entry(move |entry| {
+            let is_revisited = follow_links && is_visited_dir(&mut visited_dirs, entry);
+            let is_external = internal_roots
+                .as_ref()
+                .is_some_and(|roots| is_external_link(roots, entry));
+            let is_gitignored = gitignore
+                .as_mut()
+                .is_some_and(|gitignore| gitignore.is_ignored(entry));
+            !is_revisited
+                && !is_external
+                && !is_gitignored
+                && !is_excluded(excludes.as_ref(), &root_dir, entry)
+        });
+
+        let skips_errors = self.skips_errors();
+        let ignore_symlink = self.symlink_policy == SymlinkPolicy::Skip;
+        let extensions = self.extensions.clone();
+        let progress = Arc::clone(&self.progress);
+        let cancel_token = self.cancel_token.clone();
+        let walker = if is_too_deep { None } else { Some(walker) };
+        walker.into_iter().flatten().filter_map(move |entry| {
+            if cancel_token
+                .as_ref()
+                .is_some_and(|cancel_token| cancel_token.load(Ordering::Relaxed))
+            {
+                return Some(Err(JustOneError::Cancelled));
+            }
+            let entry = match entry {
+                Ok(val) => val,
+                Err(e) if skips_errors || is_bad_symlink(&e) => {
+                    let path = e.path()?.to_owned();
+                    return Some(Ok(Found::Ignored(path, walkdir_error!(e))));
+                }
+                Err(e) => return Some(Err(walkdir_error!(e))),
+            };
+
+            if entry.path_is_symlink() || entry.file_type().is_file() {
+                progress.files_walked.fetch_add(1, Ordering::Relaxed);
+            }
+
+            if !ignore_symlink && entry.path_is_symlink() {
+                Some(Ok(Found::Symlink(entry.into_path())))
+            } else if entry.file_type().is_file()
+                && has_wanted_extension(extensions.as_ref(), entry.path())
+            {
+                Some(found_file(entry))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Index an entry found by a traversal under the root `root`, grouping the regular
+    /// files by size in `size_dict`
+    fn index_found(
+        &mut self,
+        found: Found,
+        root: RootIndex,
+        size_dict: &mut SizeDict,
+    ) -> Result<()> {
+        let (path, file_size, times, file_id) = match found {
+            Found::File {
+                path,
+                size,
+                times,
+                file_id,
+            } => (path, size, times, file_id),
+            Found::Symlink(path) => {
+                // Processing symlinks apart, they are never compared with the regular files
+                match self.update_symlink(&path) {
+                    Ok(()) => {}
+                    Err(e) if self.skips_errors() => {
+                        let error = io_error!(e, path);
+                        self.add_ignored(path, error);
+                    }
+                    Err(e) => return Err(io_error!(e)),
+                }
+                return Ok(());
+            }
+            Found::Ignored(path, error) => {
+                self.add_ignored(path, error);
+                return Ok(());
+            }
+        };
+
+        self.progress_observer.on_item(Phase::Size);
+        // a file changed since it was indexed is indexed again, its hashes are stale
+        let index_path = self.index_path(&path);
+        if let Some(file_index) = self.lookup_file_index(&index_path) {
+            let file_info = self.get_file_info(file_index);
+            if file_info.path == *index_path
+                && (file_info.size != file_size || file_info.modified != times.modified)
+            {
+                let size = self.size_key(file_info.size);
+                remove_from_set(size_dict, &size, &file_index);
+                self.remove_file_index(file_index);
+            }
+        }
+        if (file_size == 0 && self.ignore_empty)
+            || self.min_size.is_some_and(|min_size| file_size < min_size)
+        {
+            return Ok(());
+        }
+        if self.max_size.is_some_and(|max_size| file_size > max_size) {
+            self.add_too_large(index_path.into_owned());
+            return Ok(());
+        }
+        let path: &Path = &index_path;
+        let (small_hash, full_hash) = self.cached_hashes(path, file_size, times.modified);
+        let is_new = self.lookup_file_index(path).is_none();
+        let file_index = self.add_file_info(path, root, file_size, times, small_hash, full_hash);
+        if let Some(file_id) = file_id.filter(|_| !self.report_hardlinks) {
+            self.get_file_info_mut(file_index).file_id = Some(file_id);
+            let links = self.hardlink_dict.entry(file_id).or_default();
+            match links.first() {
+                Some(&first) if first != file_index => {
+                    // another path to a file already indexed, don't read it again
+                    if !links.contains(&file_index) {
+                        links.push(file_index);
+                    }
+                    return Ok(());
+                }
+                Some(_) => {}
+                None => links.push(file_index),
+            }
+        }
+        if is_new {
+            self.send_event(|| Event::FileFound {
+                path: path.to_owned(),
+                size: file_size,
+            });
+        }
+        size_dict
+            .entry(self.size_key(file_size))
+            .or_default()
+            .insert(file_index);
+        Ok(())
+    }
+
+    /// List a file skipped for being larger than `max_size`, unless it is listed already
+    fn add_too_large(&mut self, path: PathBuf) {
+        if self.too_large_paths.insert(path.clone()) {
+            self.too_large_files.push(path);
+        }
+    }
+
+    /// Processing symbolic links separately
+    fn update_symlink(&mut self, symlink: &Path) -> io::Result<()> {
+        let link = fs::read_link(symlink)?;
+        let path = self.index_symlink_path(symlink).into_owned();
+        // a relative link is resolved from its folder, so `../x` in two folders differ
+        let mut key = normalize_lexically(&path.parent().unwrap_or(&path).join(link));
+        if self.symlink_resolve {
+            if let Ok(target) = fs::canonicalize(symlink) {
+                key = target;
+            }
+        }
+        self.symlink_hash_dict.entry(key).or_default().insert(path);
+        Ok(())
+    }
+
+    /// Return the path under which a file is indexed
+    fn index_path<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if !self.canonicalize {
+            return Cow::Borrowed(path);
+        }
+        fs::canonicalize(path)
+            .map(Cow::Owned)
+            .unwrap_or(Cow::Borrowed(path))
+    }
+
+    /// Return the path under which a symlink is indexed, only its parent is canonicalized
+    /// since the link itself must not be resolved
+    fn index_symlink_path<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(file_name)) if self.canonicalize => {
+                match fs::canonicalize(parent) {
+                    Ok(parent) => Cow::Owned(parent.join(file_name)),
+                    Err(_) => Cow::Borrowed(path),
+                }
+            }
+            _ => Cow::Borrowed(path),
+        }
+    }
+
+    /// Hash the regular files grouped by size by the traversal, and merge them into the
+    /// index
+    fn update_regular_files(&mut self, size_dict_temp: SizeDict) -> Result<HashSet<FileIndex>> {
+        let mut small_hash_dict_temp: SmallHashDict = HashMap::new();
+        let mut full_hash_dict_temp: FullHashDict = HashMap::new();
+        let mut duplicate_files_index: HashSet<FileIndex> = HashSet::new();
+
+        let sizes: Vec<FileSize> = if self.low_memory {
+            size_dict_temp.keys().copied().collect()
+        } else {
+            Vec::new()
+        };
+        let size_candidates = self.merge_size_dict(size_dict_temp);
+        if !sizes.is_empty() {
+            let linked = self.linked_files();
+            for file_size in sizes {
+                self.drop_unique_size(file_size, &linked);
+            }
+            // the dropped files were alone in their hardlink list
+            let file_info = &self.file_info;
+            self.hardlink_dict
+                .retain(|_, links| file_info[links[0]].is_some());
+        }
+
+        self.progress.start_pass(size_candidates.len());
+        self.progress
+            .size_candidates
+            .fetch_add(size_candidates.len() as u64, Ordering::Relaxed);
+        let small_hashes = self.map_files(
+            Phase::SmallHash,
+            size_candidates,
+            None,
+            |(file_size, file_index)| {
+                let file_info = self.get_file_info(file_index);
+                let hashes = if file_info.small_hash.is_none()
+                    && file_info.full_hash.is_none()
+                    && self.skips_small_hash(file_size)
+                {
+                    self.hashes_of(file_info)
+                        .map(|(small_hash, full_hash)| (small_hash, Some(full_hash)))
+                } else {
+                    self.small_hash_of(file_info)
+                        .map(|small_hash| (small_hash, None))
+                };
+                (file_size, file_index, hashes)
+            },
+        );
+        for (file_size, file_index, hashes) in small_hashes {
+            let (small_hash, full_hash) = match hashes {
+                Ok(val) => val,
+                // keep the hashes computed before, the pass stops right after
+                Err(JustOneError::Cancelled) => continue,
+                Err(e) if self.skips_errors() => {
+                    let path = self.get_file_path_by_index(file_index).to_owned();
+                    self.add_ignored(path, e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let file_info = self.get_file_info_mut(file_index);
+            file_info.small_hash = Some(small_hash);
+            if full_hash.is_some() {
+                file_info.full_hash = full_hash;
+            }
+            let key = (file_size, small_hash);
+            small_hash_dict_temp
+                .entry(key)
+                .or_default()
+                .insert(file_index);
+        }
+
+        // the partial passes are merged, so the duplicates found so far are reported
+        let small_hash_candidates = self.merge_small_hash_dict(small_hash_dict_temp);
+        self.check_cancelled()?;
+        self.progress.start_pass(small_hash_candidates.len());
+        // the files with a cached full hash are not read
+        let total_bytes = small_hash_candidates
+            .iter()
+            .map(|&file_index| self.get_file_info(file_index))
+            .filter(|file_info| file_info.full_hash.is_none())
+            .map(|file_info| self.full_hash_len(file_info.size))
+            .sum();
+        let full_hashes = self.map_files(
+            Phase::FullHash,
+            small_hash_candidates,
+            Some(total_bytes),
+            |file_index| {
+                (
+                    file_index,
+                    self.full_hash_of(self.get_file_info(file_index)),
+                )
+            },
+        );
+        for (file_index, full_hash) in full_hashes {
+            let full_hash = match full_hash {
+                Ok(val) => val,
+                // keep the hashes computed before, the pass stops right after
+                Err(JustOneError::Cancelled) => continue,
+                Err(e) if self.skips_errors() => {
+                    let path = self.get_file_path_by_index(file_index).to_owned();
+                    self.add_ignored(path, e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            self.get_file_info_mut(file_index).full_hash = Some(full_hash.clone());
+            self.send_event(|| {
+                let file_info = self.get_file_info(file_index);
+                Event::FileHashed {
+                    path: file_info.path.clone(),
+                    size: file_info.size,
+                }
+            });
+            full_hash_dict_temp
+                .entry(full_hash)
+                .or_default()
+                .insert(file_index);
+        }
+
+        let merged = self.merge_full_hash_dict(full_hash_dict_temp);
+        self.start_phase(Phase::Merge, merged.len() as u64);
+        for file_index in merged {
+            self.progress_observer.on_item(Phase::Merge);
+            duplicate_files_index.insert(file_index);
+        }
+        self.progress_observer.on_phase_end(Phase::Merge);
+        self.check_cancelled()?;
+
+        Ok(duplicate_files_index)
+    }
+
+    fn add_file_info(
+        &mut self,
+        path: &Path,
+        root: RootIndex,
+        file_size: FileSize,
+        times: FileTimes,
+        small_hash: Option<SmallHash>,
+        full_hash: Option<FullHash>,
+    ) -> FileIndex {
+        let key = self.index_key(path).into_owned();
+        self.file_index.get(&key).copied().unwrap_or_else(|| {
+            let index = self.file_info.len();
+            let old_index = self.file_index.insert(key, index);
+            debug_assert_eq!(old_index, None);
+            self.file_info.push(Some(FileInfo {
+                path: path.into(),
+                root,
+                size: file_size as FileSize,
+                modified: times.modified,
+                created: times.created,
+                small_hash,
+                full_hash,
+                file_id: None,
+            }));
+            index
+        })
+    }
+
+    fn get_file_path_by_index(&self, file_index: FileIndex) -> &Path {
+        &self.get_file_info(file_index).path
+    }
+
+    fn get_file_size(&self, path: &Path) -> FileSize {
+        self.get_file_info(self.lookup_file_index(path).unwrap())
+            .size
+    }
+
+    /// Return the key of `path` in `file_index`
+    fn index_key<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        match path.to_str() {
+            Some(path) if self.case_insensitive_paths => {
+                Cow::Owned(PathBuf::from(path.to_lowercase()))
+            }
+            _ => Cow::Borrowed(path),
+        }
+    }
+
+    /// Return the index of the file indexed under `path`
+    fn lookup_file_index(&self, path: &Path) -> Option<FileIndex> {
+        self.file_index.get(&*self.index_key(path)).copied()
+    }
+
+    fn get_file_info(&self, file_index: FileIndex) -> &FileInfo {
+        self.file_info[file_index].as_ref().unwrap()
+    }
+
+    fn get_file_info_mut(&mut self, file_index: FileIndex) -> &mut FileInfo {
+        self.file_info[file_index].as_mut().unwrap()
+    }
+
+    /// Iterate over the files still present in the index
+    fn file_infos(&self) -> impl Iterator<Item = &FileInfo> {
+        self.file_info.iter().flatten()
+    }
+
+    /// Drop the file `file_index` from the index and from every dict, its next hardlink
+    /// taking its place.
+    ///
+    /// A tombstone is left in the slot of the file. Slots are never reused, so the other
+    /// indexes stay valid.
+    fn remove_file_index(&mut self, file_index: FileIndex) {
+        let file_info = match self.file_info[file_index].take() {
+            Some(file_info) => file_info,
+            None => return,
+        };
+        self.file_index.remove(&*self.index_key(&file_info.path));
+        let size = self.size_key(file_info.size);
+        remove_from_set(&mut self.size_dict, &size, &file_index);
+        if let Some(small_hash) = file_info.small_hash {
+            remove_from_set(&mut self.small_hash_dict, &(size, small_hash), &file_index);
+        }
+        if let Some(full_hash) = &file_info.full_hash {
+            remove_from_set(&mut self.full_hash_dict, full_hash, &file_index);
+        }
+
+        if let Some(file_id) = file_info.file_id {
+            let links = self.hardlink_dict.get_mut(&file_id).unwrap();
+            let was_first = links[0] == file_index;
+            links.retain(|&link| link != file_index);
+            let successor = links.first().copied();
+            if links.is_empty() {
+                self.hardlink_dict.remove(&file_id);
+            }
+            // the next link shares the content, it takes the place of the removed one
+            if let (true, Some(successor)) = (was_first, successor) {
+                let successor_info = self.get_file_info_mut(successor);
+                successor_info.small_hash = file_info.small_hash;
+                successor_info.full_hash = file_info.full_hash.clone();
+                self.size_dict.entry(size).or_default().insert(successor);
+                if let Some(small_hash) = file_info.small_hash {
+                    self.small_hash_dict
+                        .entry((size, small_hash))
+                        .or_default()
+                        .insert(successor);
+                }
+                if let Some(full_hash) = file_info.full_hash {
+                    self.full_hash_dict
+                        .entry(full_hash)
+                        .or_default()
+                        .insert(successor);
+                }
+            }
+        }
+    }
+
+    /// Return the files with several hardlinks, which make up a hardlink group
+    fn linked_files(&self) -> HashSet<FileIndex> {
+        self.hardlink_dict
+            .values()
+            .filter(|links| links.len() > 1)
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Drop the symlinks whose path matches `predicate`, returning how many were dropped
+    fn remove_symlinks(&mut self, predicate: impl Fn(&Path) -> bool) -> usize {
+        let mut removed = 0;
+        self.symlink_hash_dict.retain(|_, symlinks| {
+            let len = symlinks.len();
+            symlinks.retain(|symlink| !predicate(symlink));
+            removed += len - symlinks.len();
+            !symlinks.is_empty()
+        });
+        removed
+    }
+
+    /// Drop the file of size `file_size` from the index if it is the only one of that size,
+    /// unless it is one of the `linked` files
+    fn drop_unique_size(&mut self, file_size: FileSize, linked: &HashSet<FileIndex>) {
+        let file_index = match self.size_dict.get(&file_size) {
+            Some(file_index_set) if file_index_set.len() == 1 => {
+                *file_index_set.iter().next().unwrap()
+            }
+            _ => return,
+        };
+        if linked.contains(&file_index) {
+            return;
+        }
+        self.size_dict.remove(&file_size);
+        if let Some(file_info) = self.file_info[file_index].take() {
+            self.file_index.remove(&*self.index_key(&file_info.path));
+        }
+    }
+
+    /// Merge the sizes found by a scan, and return the files to small-hash: the files of
+    /// the sizes shared by several files which are not in the small hash dict yet. Those
+    /// include the files indexed by previous scans but never hashed (unique size until now,
+    /// cancelled or failed pass), and exclude the files scanned again.
+    fn merge_size_dict(&mut self, size_dict_temp: SizeDict) -> Vec<(FileSize, FileIndex)> {
+        let mut merged: Vec<(FileSize, FileIndex)> = Vec::new();
+        let (file_infos, small_hash_dict) = (&self.file_info, &self.small_hash_dict);
+        for (file_size, file_index_set_temp) in size_dict_temp {
+            let file_index_set = self.size_dict.entry(file_size).or_default();
+            file_index_set.extend(file_index_set_temp);
+            if file_index_set.len() > 1 {
+                let pending = file_index_set.iter().filter(|&&file_index| {
+                    let file_info = file_infos[file_index].as_ref().unwrap();
+                    !file_info.small_hash.is_some_and(|small_hash| {
+                        is_in_dict(small_hash_dict, &(file_size, small_hash), file_index)
+                    })
+                });
+                merged.extend(pending.map(|&file_index| (file_size, file_index)));
+            }
+        }
+        merged
+    }
+
+    /// Merge the small hashes computed by a scan, and return the files to full-hash, the
+    /// same way as `merge_size_dict`
+    fn merge_small_hash_dict(&mut self, small_hash_dict_temp: SmallHashDict) -> Vec<FileIndex> {
+        let mut merged: Vec<FileIndex> = Vec::new();
+        let (file_infos, full_hash_dict) = (&self.file_info, &self.full_hash_dict);
+        for (file_size_and_small_hash, file_index_set_temp) in small_hash_dict_temp {
+            let file_index_set = self
+                .small_hash_dict
+                .entry(file_size_and_small_hash)
+                .or_default();
+            file_index_set.extend(file_index_set_temp);
+            if file_index_set.len() > 1 {
+                let pending = file_index_set.iter().filter(|&&file_index| {
+                    let file_info = file_infos[file_index].as_ref().unwrap();
+                    !file_info
+                        .full_hash
+                        .as_ref()
+                        .is_some_and(|full_hash| is_in_dict(full_hash_dict, full_hash, file_index))
+                });
+                merged.extend(pending);
+            }
+        }
+        merged
+    }
+
+    fn merge_full_hash_dict(&mut self, full_hash_dict_temp: FullHashDict) -> Vec<FileIndex> {
+        let mut merged: Vec<FileIndex> = Vec::new();
+        for (full_hash, file_index_set_temp) in full_hash_dict_temp {
+            let file_index_set = self.full_hash_dict.entry(full_hash).or_default();
+            let is_single = file_index_set.len() == 1;
+            file_index_set.extend(file_index_set_temp.iter());
+            if file_index_set.len() > 1 {
+                let set = if is_single {
+                    &*file_index_set
+                } else {
+                    &file_index_set_temp
+                };
+                merged.extend(set.iter());
+            }
+        }
+        merged
+    }
+
+    /// Map `f` over the candidate files of a hashing pass, on the thread pool if there is one.
+    /// `total_bytes` is reported to the observer if the pass reads whole files.
+    fn map_files<T, R, F>(
+        &self,
+        phase: Phase,
+        items: Vec<T>,
+        total_bytes: Option<u64>,
+        f: F,
+    ) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Sync + Send,
+    {
+        self.start_phase(phase, items.len() as u64);
+        if let Some(total_bytes) = total_bytes {
+            self.progress_observer.on_bytes_start(phase, total_bytes);
+        }
+        let f = |item| {
+            let result = f(item);
+            self.progress.files_pending.fetch_sub(1, Ordering::Relaxed);
+            self.progress_observer.on_item(phase);
+            result
+        };
+        let results = match &self.thread_pool {
+            Some(pool) => pool.install(|| items.into_par_iter().map(f).collect()),
+            None => items.into_iter().map(f).collect(),
+        };
+        self.progress_observer.on_phase_end(phase);
+        results
+    }
+
+    /// Tell the observer and the event sender that a pass of `total` items starts
+    fn start_phase(&self, phase: Phase, total: u64) {
+        self.progress_observer.on_phase_start(phase, total);
+        self.send_event(|| Event::PhaseStarted(phase));
+    }
+
+    /// Return the hash of a fixed probe by the hasher creator, to tell whether two indexes
+    /// use the same hasher (and seed)
+    fn hasher_fingerprint(&self) -> u64 {
+        let mut hasher = (self.hasher_creator)();
+        hasher.write(b"justone");
+        hasher.finish()
+    }
+
+    /// Return true if a file failing with an error is skipped rather than failing the scan
+    fn skips_errors(&self) -> bool {
+        self.error_policy != ErrorPolicy::FailFast
+    }
+
+    /// Return `JustOneError::Cancelled` if the cancel token is set
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancel_token {
+            Some(cancel_token) if cancel_token.load(Ordering::Relaxed) => {
+                Err(JustOneError::Cancelled)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the cached small hash of the file or compute it, without caching it
+    fn small_hash_of(&self, file_info: &FileInfo) -> Result<SmallHash> {
+        if let Some(hash) = file_info.small_hash {
+            return Ok(hash);
+        }
+        self.check_cancelled()?;
+        let path = &file_info.path;
+        let hasher_creator = self.hasher_creator.as_ref();
+        let hash = retry_transient(self.retry_policy, || {
+            let f = self.counting_reader(File::open(path)?, Phase::SmallHash);
+            let mut f = self.content_reader(f)?;
+            get_small_hash(&mut f, HasherDigest(hasher_creator()), self.small_hash_size)
+        })
+        .map_err(|e| io_error!(e, path))?;
+        self.progress.small_hashes.fetch_add(1, Ordering::Relaxed);
+        Ok(hash)
+    }
+
+    /// Compute the small and the full hash of the file in a single read, without caching
+    /// them, for the files which skip the small hash pass. The file is read through the
+    /// buffer even with mmap on.
+    fn hashes_of(&self, file_info: &FileInfo) -> Result<(SmallHash, FullHash)> {
+        self.check_cancelled()?;
+        let path = &file_info.path;
+        let hasher_creator = self.hasher_creator.as_ref();
+        let hashes = retry_transient(self.retry_policy, || {
+            let mut f = self.counting_reader(File::open(path)?, Phase::SmallHash);
+            let mut head = Vec::with_capacity(self.small_hash_size);
+            (&mut f)
+                .take(self.small_hash_size as u64)
+                .read_to_end(&mut head)?;
+            let small_hash =
+                get_small_hash(&mut &head[..], HasherDigest(hasher_creator()), head.len())?;
+            let len = self.full_hash_len(file_info.size);
+            let mut f = (&head[..]).chain(f).take(len);
+            let buffer_size = self.read_buffer_size.min((len as usize).max(1));
+            let full_hash =
+                get_full_digest(&mut f, self.hash_algorithm, hasher_creator, buffer_size)?;
+            Ok((small_hash, full_hash))
+        })
+        .map_err(|e| io_error!(e, path))?;
+        self.progress.small_hashes.fetch_add(1, Ordering::Relaxed);
+        self.progress.files_hashed.fetch_add(1, Ordering::Relaxed);
+        Ok(hashes)
+    }
+
+    /// Return true if the files of `size` bytes get their full hash along with their small
+    /// hash, see `with_skip_small_hash`
+    fn skips_small_hash(&self, size: FileSize) -> bool {
+        !self.normalize_text && self.skip_small_hash.unwrap_or(size <= self.small_hash_size)
+    }
+
+    /// Return the key of the files of `size` bytes in the size dict, the same for all of
+    /// them if the text is normalized since its size changes
+    fn size_key(&self, size: FileSize) -> FileSize {
+        if self.normalize_text {
+            0
+        } else {
+            size
+        }
+    }
+
+    /// Return the cached full hash of the file or compute it, without caching it
+    fn full_hash_of(&self, file_info: &FileInfo) -> Result<FullHash> {
+        if let Some(hash) = &file_info.full_hash {
+            return Ok(hash.clone());
+        }
+        self.check_cancelled()?;
+        let path = &file_info.path;
+        let hasher_creator = self.hasher_creator.as_ref();
+        let hash = retry_transient(self.retry_policy, || {
+            let file = File::open(path)?;
+            let len = self.full_hash_len(file_info.size);
+            if let Some(mmap) = self.map_file(&file, file_info.size)? {
+                let bytes = &mmap[..len as usize];
+                self.progress
+                    .bytes_read
+                    .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                self.progress_observer
+                    .on_bytes(Phase::FullHash, bytes.len() as u64);
+                if let Some(limiter) = &self.io_rate_limit {
+                    limiter.acquire(bytes.len() as u64);
+                }
+                return Ok(get_full_hash_of_bytes(
+                    bytes,
+                    self.hash_algorithm,
+                    hasher_creator,
+                ));
+            }
+            let mut f = self
+                .content_reader(self.counting_reader(file, Phase::FullHash))?
+                .take(len);
+            // a small file doesn't need the whole buffer
+            let buffer_size = self.read_buffer_size.min((len as usize).max(1));
+            get_full_digest(&mut f, self.hash_algorithm, hasher_creator, buffer_size)
+        })
+        .map_err(|e| io_error!(e, path))?;
+        self.progress.files_hashed.fetch_add(1, Ordering::Relaxed);
+        Ok(hash)
+    }
+
+    /// Return how many bytes of a file of `size` bytes the full hash covers
+    fn full_hash_len(&self, size: FileSize) -> u64 {
+        let size = size as u64;
+        self.full_hash_limit.map_or(size, |limit| size.min(limit))
+    }
+
+    /// Wrap `file` to report the bytes read during `phase` to the counters and the observer
+    fn counting_reader(&self, file: File, phase: Phase) -> CountingReader<'_, File> {
+        CountingReader {
+            inner: file,
+            counter: &self.progress.bytes_read,
+            observer: self.progress_observer.as_ref(),
+            limiter: self.io_rate_limit.as_ref(),
+            phase,
+        }
+    }
+
+    /// Map `file` in memory if mmap is on and it is a non-empty regular file which still has
+    /// the size it had when indexed. The normalized text is read through a buffer anyway.
+    fn map_file(&self, file: &File, size: FileSize) -> io::Result<Option<Mmap>> {
+        if !self.mmap || self.normalize_text {
+            return Ok(None);
+        }
+        let metadata = file.metadata()?;
+        if !metadata.is_file()
+            || metadata.len() == 0
+            || metadata.len() > MMAP_MAX_SIZE
+            || metadata.len() != size as u64
+        {
+            return Ok(None);
+        }
+        // SAFETY: the mapping is only read while hashing, and `with_mmap` documents that the
+        // file must not be truncated meanwhile
+        unsafe { Mmap::map(file) }.map(Some)
+    }
+}
+
+/// A reader adding the count of the bytes read to a shared counter, and reporting it to the
+/// progress observer and the rate limiter
+struct CountingReader<'a, R> {
+    inner: R,
+    counter: &'a AtomicU64,
+    observer: &'a dyn ProgressObserver,
+    limiter: Option<&'a RateLimiter>,
+    phase: Phase,
+}
+
+impl<R: io::Read> io::Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read_size = self.inner.read(buf)?;
+        self.counter.fetch_add(read_size as u64, Ordering::Relaxed);
+        self.observer.on_bytes(self.phase, read_size as u64);
+        if let Some(limiter) = self.limiter {
+            limiter.acquire(read_size as u64);
+        }
+        Ok(read_size)
+    }
+}
+
+/// Hash the first `size` bytes of `f`, or all of it if it is shorter. The small hash keeps
+/// the first `SMALL_HASH_LEN` bytes of wider digests.
+fn get_small_hash<D: Digest>(
+    f: &mut dyn Read,
+    mut digest: D,
+    size: usize,
+) -> io::Result<SmallHash> {
+    let mut buffer = Vec::with_capacity(size);
+    f.take(size as u64).read_to_end(&mut buffer)?;
+    digest.update(&buffer);
+    let hash = digest.finalize();
+    let mut bytes = [0; SMALL_HASH_LEN];
+    let len = hash.len().min(SMALL_HASH_LEN);
+    bytes[..len].copy_from_slice(&hash[..len]);
+    Ok(SmallHash(bytes))
+}
+
+fn get_full_hash<D: Digest>(
+    f: &mut dyn io::Read,
+    mut digest: D,
+    buffer_size: usize,
+) -> io::Result<FullHash> {
+    for_each_chunk(f, buffer_size, |chunk| digest.update(chunk))?;
+    Ok(FullHash(digest.finalize()))
+}
+
+/// Compute the full hash with `algorithm`, `XxHash64` going through the hasher creator
+fn get_full_digest(
+    f: &mut dyn io::Read,
+    algorithm: HashAlgorithm,
+    hasher_creator: &(dyn Fn() -> Box<dyn Hasher> + Send + Sync),
+    buffer_size: usize,
+) -> io::Result<FullHash> {
+    match algorithm {
+        HashAlgorithm::XxHash64 => get_full_hash(f, HasherDigest(hasher_creator()), buffer_size),
+        HashAlgorithm::Sha256 => get_full_hash(f, Sha256::default(), buffer_size),
+        HashAlgorithm::Blake3 => get_full_hash(f, blake3::Hasher::new(), buffer_size),
+    }
+}
+
+/// Compute the full hash of a whole content in memory, in a single update of the digest
+fn get_full_hash_of_bytes(
+    bytes: &[u8],
+    algorithm: HashAlgorithm,
+    hasher_creator: &(dyn Fn() -> Box<dyn Hasher> + Send + Sync),
+) -> FullHash {
+    fn digest_of<D: Digest>(mut digest: D, bytes: &[u8]) -> FullHash {
+        digest.update(bytes);
+        FullHash(digest.finalize())
+    }
+    match algorithm {
+        HashAlgorithm::XxHash64 => digest_of(HasherDigest(hasher_creator()), bytes),
+        HashAlgorithm::Sha256 => digest_of(Sha256::default(), bytes),
+        HashAlgorithm::Blake3 => digest_of(blake3::Hasher::new(), bytes),
+    }
+}
+
+/// Read `f` to the end by chunks of at most `buffer_size` bytes, passing each to `consume`
+fn for_each_chunk(
+    f: &mut dyn io::Read,
+    buffer_size: usize,
+    mut consume: impl FnMut(&[u8]),
+) -> io::Result<()> {
+    let mut buffer = vec![0; buffer_size];
+    loop {
+        let read_size = f.read(&mut buffer)?;
+        if read_size == 0 {
+            return Ok(());
+        }
+        consume(&buffer[..read_size]);
+    }
+}
+
+/// Return the (device, inode) of a file having several hardlinks
+#[cfg(unix)]
+fn get_file_id(metadata: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.nlink() > 1 {
+        Some((metadata.dev(), metadata.ino()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn get_file_id(_metadata: &fs::Metadata) -> Option<FileId> {
+    None
+}
+
+/// Return the (device, inode) of a folder
+#[cfg(unix)]
+fn get_dir_id(metadata: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn get_dir_id(_metadata: &fs::Metadata) -> Option<FileId> {
+    None
+}
+
+/// Return the device holding a file
+#[cfg(unix)]
+fn get_device(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn get_device(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Return true if no extension filter is set or `path` has one of its extensions
+fn has_wanted_extension(extensions: Option<&HashSet<String>>, path: &Path) -> bool {
+    match extensions {
+        None => true,
+        Some(exts) => path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| exts.contains(&ext.to_lowercase())),
+    }
+}
+
+/// Read what the index needs of the regular file `entry`
+fn found_file(entry: DirEntry) -> Result<Found> {
+    let metadata = entry.metadata().map_err(|e| walkdir_error!(e))?;
+    Ok(Found::file(entry.into_path(), &metadata))
+}
+
+/// Return true if the entry, relative to `root`, matches one of the exclude patterns
+fn is_excluded(excludes: Option<&GlobSet>, root: &Path, entry: &DirEntry) -> bool {
+    match (excludes, entry.path().strip_prefix(root)) {
+        (Some(excludes), Ok(relative_path)) if !relative_path.as_os_str().is_empty() => {
+            excludes.is_match(relative_path)
+        }
+        _ => false,
+    }
+}
+
+/// Remove `value` from the set of `key`, and the set once empty
+fn remove_from_set<K: Hash + Eq, V: Hash + Eq>(
+    dict: &mut HashMap<K, HashSet<V>>,
+    key: &K,
+    value: &V,
+) {
+    if let Some(set) = dict.get_mut(key) {
+        set.remove(value);
+        if set.is_empty() {
+            dict.remove(key);
+        }
+    }
+}
+
+/// Return true if `entry` is a folder already seen by this traversal, remembering it otherwise
+fn is_visited_dir(visited_dirs: &mut HashSet<FileId>, entry: &DirEntry) -> bool {
+    if !entry.file_type().is_dir() {
+        return false;
+    }
+    match entry.metadata().ok().as_ref().and_then(get_dir_id) {
+        Some(dir_id) => !visited_dirs.insert(dir_id),
+        None => false,
+    }
+}
+
+/// Return true if `entry` is a symlink whose target isn't under any of the canonical `roots`
+fn is_external_link(roots: &[PathBuf], entry: &DirEntry) -> bool {
+    entry.path_is_symlink()
+        && !fs::canonicalize(entry.path())
+            .is_ok_and(|target| roots.iter().any(|root| target.starts_with(root)))
+}
+
+/// Remove the `.` and `..` components of `path` without reading the filesystem, a `..`
+/// cancels the previous component even if it is a symlink
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Return true if the traversal failed on a symlink loop or a symlink whose target is
+/// missing, which are skipped even with `ErrorPolicy::FailFast`
+fn is_bad_symlink(err: &walkdir::Error) -> bool {
+    if err.loop_ancestor().is_some() {
+        return true;
+    }
+    let is_not_found = err
+        .io_error()
+        .is_some_and(|e| e.kind() == io::ErrorKind::NotFound);
+    let is_symlink = err
+        .path()
+        .and_then(|path| fs::symlink_metadata(path).ok())
+        .is_some_and(|metadata| metadata.file_type().is_symlink());
+    is_not_found && is_symlink
+}
+
+/// Split a group of files sharing their full hash into the groups of files with the same
+/// content, compared byte by byte (or by stat first if `shallow`). The files alone in
+/// their group are left out.
+fn split_identical(group: Vec<&Path>, shallow: bool) -> Result<Vec<Vec<&Path>>> {
+    let mut identical_groups: Vec<Vec<&Path>> = Vec::new();
+    for file in group {
+        let mut matched = false;
+        for identical_files in &mut identical_groups {
+            if file_cmp(file, identical_files[0], shallow)? {
+                identical_files.push(file);
+                matched = true;
+                break;
+            }
+        }
+        if !matched {
+            identical_groups.push(vec![file]);
+        }
+    }
+    identical_groups.retain(|identical_files| identical_files.len() > 1);
+    Ok(identical_groups)
+}
+
+/// Split a group of identical files by file name, keeping their order. Paths without a file
+/// name never match another path.
+fn split_by_name(group: Vec<&Path>) -> Vec<Vec<&Path>> {
+    let mut name_groups: Vec<Vec<&Path>> = Vec::new();
+    let mut group_of_name: HashMap<_, usize> = HashMap::new();
+    for path in group {
+        match path.file_name() {
+            Some(name) => match group_of_name.get(name) {
+                Some(&i) => name_groups[i].push(path),
+                None => {
+                    group_of_name.insert(name, name_groups.len());
+                    name_groups.push(vec![path]);
+                }
+            },
+            None => continue,
+        }
+    }
+    name_groups.retain(|paths| paths.len() > 1);
+    name_groups
+}
+
+/// Return true if `file_index` is filed under `key` in one of the size or hash dicts
+fn is_in_dict<K: Eq + Hash>(
+    dict: &HashMap<K, HashSet<FileIndex>>,
+    key: &K,
+    file_index: FileIndex,
+) -> bool {
+    dict.get(key)
+        .is_some_and(|file_index_set| file_index_set.contains(&file_index))
+}
+
+/// Turn the result of an eager comparison into an iterator of groups
+fn eager_groups(groups: Result<Vec<Vec<&Path>>>) -> DuplicateGroups<'_> {
+    match groups {
+        Ok(groups) => Box::new(groups.into_iter().map(Ok)),
+        Err(e) => Box::new(std::iter::once(Err(e))),
+    }
+}
+
+/// Run `op` again when it fails with an error kind that is likely to go away by itself
+fn retry_transient<T>(policy: RetryPolicy, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delay = policy.delay;
+    for _ in 0..policy.count {
+        match op() {
+            Err(e) if is_transient(&e) => {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    op()
+}
+
+/// Return the kind of the IO error behind `err`, `Other` if there is none
+fn error_kind(err: &JustOneError) -> io::ErrorKind {
+    match err {
+        JustOneError::IOError { error, .. } => error.kind(),
+        JustOneError::WalkdirError(e) => e.io_error().map_or(io::ErrorKind::Other, io::Error::kind),
+        _ => io::ErrorKind::Other,
+    }
+}
+
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+fn file_cmp(file_a: impl AsRef<Path>, file_b: impl AsRef<Path>, shallow: bool) -> Result<bool> {
+    filecmp::cmp(&file_a, &file_b, shallow).map_err(|e| io_error!(e, file_a, file_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::io::Read;
+
+    /// A reader failing with `kind` until `failures` is exhausted, shared between attempts
+    struct FlakyReader<'a> {
+        inner: &'a [u8],
+        failures: &'a Cell<usize>,
+        kind: io::ErrorKind,
+    }
+
+    impl Read for FlakyReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(io::Error::from(self.kind));
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_retry_transient() {
+        let hasher_creator = default_hasher_creator();
+        let policy = RetryPolicy {
+            count: 3,
+            delay: Duration::from_millis(1),
+        };
+        let hash_flaky = |failures: &Cell<usize>, kind, policy| {
+            retry_transient(policy, || {
+                let mut f = FlakyReader {
+                    inner: &b"abc"[..],
+                    failures,
+                    kind,
+                };
+                get_full_hash(
+                    &mut f,
+                    HasherDigest(hasher_creator()),
+                    READ_BUFFER_SIZE_DEFAULT,
+                )
+            })
+        };
+
+        let failures = Cell::new(3);
+        let hash = hash_flaky(&failures, io::ErrorKind::TimedOut, policy).unwrap();
+        assert_eq!(
+            FullHash(0x44bc2cf5ad770999_u64.to_le_bytes().to_vec()),
+            hash
+        ); // xxh64
+
+        let failures = Cell::new(4);
+        let err = hash_flaky(&failures, io::ErrorKind::TimedOut, policy).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        let failures = Cell::new(1);
+        let err = hash_flaky(&failures, io::ErrorKind::NotFound, policy).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(failures.get(), 0);
+    }
+
+    #[test]
+    fn test_get_small_hash() {
+        let hasher_creator = default_hasher_creator();
 
         let mut f = &[b'0'; 12345][..];
-        let SmallHash(hash_val) = get_small_hash(&mut f, hasher_creator()).unwrap();
-        assert_eq!("908a9517d970b2c6", format!("{:016x}", hash_val)); // xxh64
+        let hash = get_small_hash(
+            &mut f,
+            HasherDigest(hasher_creator()),
+            SMALL_HASH_SIZE_DEFAULT,
+        )
+        .unwrap();
+        assert_eq!(SmallHash(0x908a9517d970b2c6_u64.to_le_bytes()), hash); // xxh64
 
         let mut f = &b"abc"[..];
-        let SmallHash(hash_val) = get_small_hash(&mut f, hasher_creator()).unwrap();
-        assert_eq!("44bc2cf5ad770999", format!("{:016x}", hash_val)); // xxh64
+        let hash = get_small_hash(
+            &mut f,
+            HasherDigest(hasher_creator()),
+            SMALL_HASH_SIZE_DEFAULT,
+        )
+        .unwrap();
+        assert_eq!(SmallHash(0x44bc2cf5ad770999_u64.to_le_bytes()), hash); // xxh64
 
         let mut f = &b""[..];
-        let SmallHash(hash_val) = get_small_hash(&mut f, hasher_creator()).unwrap();
-        assert_eq!("ef46db3751d8e999", format!("{:016x}", hash_val)); // xxh64
+        let hash = get_small_hash(
+            &mut f,
+            HasherDigest(hasher_creator()),
+            SMALL_HASH_SIZE_DEFAULT,
+        )
+        .unwrap();
+        assert_eq!(SmallHash(0xef46db3751d8e999_u64.to_le_bytes()), hash); // xxh64
+
+        let mut f = &b"abc"[..];
+        let hash = get_small_hash(&mut f, Sha256::default(), SMALL_HASH_SIZE_DEFAULT).unwrap();
+        assert_eq!(SmallHash(0xba7816bf8f01cfea_u64.to_be_bytes()), hash); // sha256, first 64 bits
     }
 
     #[test]
@@ -564,15 +2880,136 @@ mod tests {
         let hasher_creator = default_hasher_creator();
 
         let mut f = &[b'0'; 12345][..];
-        let FullHash(hash_val) = get_full_hash(&mut f, hasher_creator()).unwrap();
-        assert_eq!("8052320d3bcad6a7", format!("{:016x}", hash_val)); // xxh64
+        let hash = get_full_hash(
+            &mut f,
+            HasherDigest(hasher_creator()),
+            READ_BUFFER_SIZE_DEFAULT,
+        )
+        .unwrap();
+        assert_eq!(
+            FullHash(0x8052320d3bcad6a7_u64.to_le_bytes().to_vec()),
+            hash
+        ); // xxh64
 
         let mut f = &b"abc"[..];
-        let FullHash(hash_val) = get_full_hash(&mut f, hasher_creator()).unwrap();
-        assert_eq!("44bc2cf5ad770999", format!("{:016x}", hash_val)); // xxh64
+        let hash = get_full_hash(
+            &mut f,
+            HasherDigest(hasher_creator()),
+            READ_BUFFER_SIZE_DEFAULT,
+        )
+        .unwrap();
+        assert_eq!(
+            FullHash(0x44bc2cf5ad770999_u64.to_le_bytes().to_vec()),
+            hash
+        ); // xxh64
 
         let mut f = &b""[..];
-        let FullHash(hash_val) = get_full_hash(&mut f, hasher_creator()).unwrap();
-        assert_eq!("ef46db3751d8e999", format!("{:016x}", hash_val)); // xxh64
+        let hash = get_full_hash(
+            &mut f,
+            HasherDigest(hasher_creator()),
+            READ_BUFFER_SIZE_DEFAULT,
+        )
+        .unwrap();
+        assert_eq!(
+            FullHash(0xef46db3751d8e999_u64.to_le_bytes().to_vec()),
+            hash
+        ); // xxh64
+    }
+
+    #[test]
+    fn test_get_full_hash_buffer_size() {
+        let content = b"The quick brown fox jumps over the lazy dog";
+        for &algorithm in &[
+            HashAlgorithm::XxHash64,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake3,
+        ] {
+            let hashes: Vec<FullHash> = [1, 7, content.len(), READ_BUFFER_SIZE_DEFAULT]
+                .iter()
+                .map(|&buffer_size| {
+                    let mut f = &content[..];
+                    get_full_digest(&mut f, algorithm, &*default_hasher_creator(), buffer_size)
+                        .unwrap()
+                })
+                .collect();
+            assert!(hashes.windows(2).all(|pair| pair[0] == pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_get_full_digest() {
+        let mut f = &b"abc"[..];
+        let hash = get_full_hash(&mut f, Sha256::default(), READ_BUFFER_SIZE_DEFAULT).unwrap();
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            format!("{:x}", hash)
+        ); // sha256
+
+        let mut f = &b"abc"[..];
+        let hash = get_full_hash(&mut f, blake3::Hasher::new(), READ_BUFFER_SIZE_DEFAULT).unwrap();
+        assert_eq!(
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            format!("{:x}", hash)
+        ); // blake3
+    }
+
+    #[test]
+    fn test_get_full_hash_of_bytes() {
+        let bytes = b"The quick brown fox jumps over the lazy dog";
+        for algorithm in [
+            HashAlgorithm::XxHash64,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake3,
+        ] {
+            let mut f = &bytes[..];
+            let hasher_creator = default_hasher_creator();
+            let buffered = get_full_digest(
+                &mut f,
+                algorithm,
+                &*hasher_creator,
+                READ_BUFFER_SIZE_DEFAULT,
+            );
+            let hash = get_full_hash_of_bytes(bytes, algorithm, &*hasher_creator);
+            assert_eq!(buffered.unwrap(), hash);
+        }
+    }
+
+    #[test]
+    fn test_content_reader_normalizes_text() {
+        let jo = JustOne::new().with_normalize_text(true);
+        let normalized = |bytes: &[u8]| {
+            let mut content = Vec::new();
+            jo.content_reader(bytes)
+                .unwrap()
+                .read_to_end(&mut content)
+                .unwrap();
+            content
+        };
+        assert_eq!(normalized(b"a \t\r\nb\rc\r\r\n d  "), b"a\nb\nc\n\n d");
+        assert_eq!(normalized(b"a \r\n\0"), b"a \r\n\0");
+        assert_eq!(normalized(b""), b"");
+    }
+
+    #[test]
+    fn test_ignored_file_serialize() {
+        let ignored = IgnoredFile {
+            path: PathBuf::from("a"),
+            kind: io::ErrorKind::PermissionDenied,
+        };
+        let json = serde_json::to_string(&ignored).unwrap();
+        assert_eq!(json, r#"{"path":"a","kind":"permission denied"}"#);
+    }
+
+    #[test]
+    fn test_progress_is_off_by_default() {
+        // the no-op observer is the only one without any state
+        let mut jo = JustOne::new();
+        assert_eq!(std::mem::size_of_val(&*jo.progress_observer), 0);
+        jo.set_progress(true);
+        assert_ne!(std::mem::size_of_val(&*jo.progress_observer), 0);
+        jo.set_progress(false);
+        assert_eq!(std::mem::size_of_val(&*jo.progress_observer), 0);
+        let jo = JustOne::builder().show_progress(true).build();
+        assert_ne!(std::mem::size_of_val(&*jo.progress_observer), 0);
     }
 }
@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::ImageFormat;
+use rayon::prelude::*;
+
+use crate::{JustOne, Result};
+
+impl JustOne {
+    /// Group the images whose perceptual hashes differ by at most `max_distance` bits (out
+    /// of 64) in `duplicates_perceptual`. 0 only matches images which look the same once
+    /// scaled down, around 10 also matches re-encoded or resized copies.
+    pub fn with_perceptual_distance(mut self, max_distance: u32) -> Self {
+        self.perceptual_distance = max_distance;
+        self
+    }
+
+    /// Return the groups of images which look alike, e.g. a photo and its resized or
+    /// re-encoded copies, along with the duplicate groups of the other files.
+    ///
+    /// The images are the indexed files with an image extension which can be decoded, they
+    /// are compared by the difference hash of their content within the distance set by
+    /// `with_perceptual_distance`, each image against all the others. The other files are
+    /// compared exactly, like `duplicates`. With `with_low_memory`, the images whose size
+    /// was unique are not compared.
+    pub fn duplicates_perceptual(&self) -> Result<Vec<Vec<&Path>>> {
+        let images: Vec<&Path> = self
+            .file_infos()
+            .map(|file_info| file_info.path.as_ref())
+            .filter(|path| ImageFormat::from_path(path).is_ok())
+            .collect();
+        let hash = |path| difference_hash(path).map(|hash| (path, hash));
+        let hashes: Vec<(&Path, u64)> = match &self.thread_pool {
+            Some(pool) => pool.install(|| images.into_par_iter().filter_map(&hash).collect()),
+            None => images.into_iter().filter_map(&hash).collect(),
+        };
+
+        let mut groups = group_within(&hashes, self.perceptual_distance);
+        let hashed: HashSet<&Path> = hashes.iter().map(|(path, _)| *path).collect();
+        for group in self.duplicate_groups() {
+            let group: Vec<&Path> = group?
+                .into_iter()
+                .filter(|path| !hashed.contains(path))
+                .collect();
+            if group.len() > 1 {
+                groups.push(group);
+            }
+        }
+
+        for group in &mut groups {
+            group.sort_unstable();
+        }
+        groups.sort_unstable();
+        Ok(groups)
+    }
+}
+
+/// Return the difference hash of an image: one bit per pair of neighbouring pixels of its
+/// 9x8 grayscale thumbnail, set if the left one is darker. `None` if it can't be decoded.
+fn difference_hash(path: &Path) -> Option<u64> {
+    let thumbnail = image::open(path)
+        .ok()?
+        .resize_exact(9, 8, FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if thumbnail.get_pixel(x, y)[0] < thumbnail.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Some(hash)
+}
+
+/// Group the paths whose hashes are within `max_distance` bits, transitively
+fn group_within<'a>(hashes: &[(&'a Path, u64)], max_distance: u32) -> Vec<Vec<&'a Path>> {
+    let mut parents: Vec<usize> = (0..hashes.len()).collect();
+    fn find(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    for i in 0..hashes.len() {
+        for j in i + 1..hashes.len() {
+            if (hashes[i].1 ^ hashes[j].1).count_ones() <= max_distance {
+                let (root_i, root_j) = (find(&mut parents, i), find(&mut parents, j));
+                parents[root_j] = root_i;
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<&Path>> = vec![Vec::new(); hashes.len()];
+    for (i, (path, _)) in hashes.iter().enumerate() {
+        let root = find(&mut parents, i);
+        groups[root].push(path);
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+const BYTES_TEMPLATE: &str = "{wide_bar} {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta})";
+const SIZE_TEMPLATE: &str = "{spinner} {pos} files found";
+
+/// The passes of an `update`, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Reading the size of the files as the traversal finds them
+    Size,
+    /// Hashing the beginning of the files sharing their size with another one
+    SmallHash,
+    /// Hashing the whole content of the files sharing their small hash with another one
+    FullHash,
+    /// Collecting the files which turned out to be duplicates
+    Merge,
+}
+
+/// Receive the progress of the passes of `JustOne::update`.
+///
+/// Items may be reported from several threads when hashing in parallel.
+pub trait ProgressObserver: Send + Sync {
+    /// A pass starts, `total` items are going to be reported. It is 0 for `Phase::Size`,
+    /// which runs along the traversal, so the number of files isn't known beforehand.
+    fn on_phase_start(&self, phase: Phase, total: u64);
+
+    /// One more item of the pass is done
+    fn on_item(&self, phase: Phase);
+
+    /// The pass reads whole files, `total_bytes` are going to be reported by `on_bytes`.
+    /// Called right after `on_phase_start` for `Phase::FullHash`.
+    fn on_bytes_start(&self, _phase: Phase, _total_bytes: u64) {}
+
+    /// `bytes` more bytes were read during the pass
+    fn on_bytes(&self, _phase: Phase, _bytes: u64) {}
+
+    /// The pass is over
+    fn on_phase_end(&self, _phase: Phase) {}
+}
+
+/// Render each pass as an indicatif progress bar on stderr, the observer installed by
+/// `JustOne::set_progress(true)`. The passes reading whole files count bytes rather than
+/// files, with an ETA.
+#[derive(Debug, Default)]
+pub struct BarObserver {
+    /// The bar of the current pass, and whether it counts bytes
+    bar: Mutex<Option<(ProgressBar, bool)>>,
+    /// Where the bars are drawn, if not on their own
+    multi: Option<Arc<MultiProgress>>,
+}
+
+impl BarObserver {
+    pub fn new() -> Self {
+        BarObserver::default()
+    }
+
+    /// Add the bars to `multi` instead of drawing them on their own, so a larger tool can
+    /// render them along with its own bars. Drawing them is then up to `multi`.
+    pub fn with_multi_progress(multi: Arc<MultiProgress>) -> Self {
+        BarObserver {
+            bar: Mutex::default(),
+            multi: Some(multi),
+        }
+    }
+}
+
+impl ProgressObserver for BarObserver {
+    fn on_phase_start(&self, phase: Phase, total: u64) {
+        let bar = if phase == Phase::Size {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(ProgressStyle::default_spinner().template(SIZE_TEMPLATE));
+            bar
+        } else {
+            ProgressBar::new(total)
+        };
+        let bar = match &self.multi {
+            Some(multi) => multi.add(bar),
+            None => bar,
+        };
+        *self.bar.lock().unwrap() = Some((bar, false));
+    }
+
+    fn on_item(&self, _phase: Phase) {
+        if let Some((bar, false)) = &*self.bar.lock().unwrap() {
+            bar.inc(1);
+        }
+    }
+
+    fn on_bytes_start(&self, _phase: Phase, total_bytes: u64) {
+        if let Some((bar, counts_bytes)) = &mut *self.bar.lock().unwrap() {
+            bar.set_length(total_bytes);
+            bar.set_style(ProgressStyle::default_bar().template(BYTES_TEMPLATE));
+            *counts_bytes = true;
+        }
+    }
+
+    fn on_bytes(&self, _phase: Phase, bytes: u64) {
+        if let Some((bar, true)) = &*self.bar.lock().unwrap() {
+            bar.inc(bytes);
+        }
+    }
+
+    fn on_phase_end(&self, _phase: Phase) {
+        if let Some((bar, _)) = self.bar.lock().unwrap().take() {
+            bar.finish();
+        }
+    }
+}
+
+/// Ignore the progress, scanning silently, the default observer
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopObserver;
+
+impl ProgressObserver for NoopObserver {
+    fn on_phase_start(&self, _phase: Phase, _total: u64) {}
+
+    fn on_item(&self, _phase: Phase) {}
+}
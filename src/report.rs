@@ -0,0 +1,178 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::result;
+
+use serde::{Serialize, Serializer};
+
+use crate::{FileSize, HashAlgorithm, IgnoredFile, JustOne, Result, Stats};
+
+/// Everything a scan found, owned so it can outlive the index, e.g. to serialize it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// The duplicate groups of regular files, in the order of `duplicates_sorted`
+    pub duplicates: Vec<DuplicateGroup>,
+    /// The groups of symlinks with the same target, empty if the symlinks are followed or
+    /// ignored
+    pub symlinks: Vec<Vec<PathBuf>>,
+    pub stats: Stats,
+    /// Set if the full hashes only covered this many bytes from the start of the files, the
+    /// duplicates are then only candidates requiring confirmation
+    pub full_hash_limit: Option<u64>,
+    /// The files skipped because of an error, see `JustOne::ignored`
+    pub skipped: Vec<IgnoredFile>,
+}
+
+/// A group of identical regular files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateGroup {
+    /// The size of each file of the group
+    pub size: FileSize,
+    pub paths: Vec<PathBuf>,
+}
+
+/// A group of identical regular files along with their full hash, see
+/// `JustOne::duplicates_with_hash`. It displays as a header like
+/// `xxh64:8052320d3bcad6a7 (3 files, 1.2 MiB each)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashedGroup<'a> {
+    pub algorithm: HashAlgorithm,
+    /// The full hash of the files, in lowercase hex
+    pub hash: String,
+    /// The size of each file of the group
+    pub size: FileSize,
+    pub paths: Vec<&'a Path>,
+}
+
+impl fmt::Display for HashedGroup<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} ({} files, {} each)",
+            self.algorithm,
+            self.hash,
+            self.paths.len(),
+            format_size(self.size as u64)
+        )
+    }
+}
+
+/// The headline numbers of the duplicates of regular files, see `JustOne::summary`. It
+/// displays as `Found 42 duplicate groups, 137 redundant files, 3.4 GiB reclaimable.`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub groups: usize,
+    /// The files beyond the first of each group
+    pub redundant_files: usize,
+    /// The bytes freed by keeping only one file of each group, see `JustOne::wasted_bytes`
+    pub reclaimable_bytes: u64,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Found {} duplicate groups, {} redundant files, {} reclaimable.",
+            self.groups,
+            self.redundant_files,
+            format_size(self.reclaimable_bytes)
+        )
+    }
+}
+
+impl JustOne {
+    /// Count the duplicate groups of regular files, their redundant files and the space
+    /// they take. Symlinks are left out.
+    pub fn summary(&self) -> Result<Summary> {
+        let mut summary = Summary::default();
+        for (size, group) in self.duplicates_with_size()? {
+            summary.groups += 1;
+            summary.redundant_files += group.len() - 1;
+            summary.reclaimable_bytes += size as u64 * (group.len() as u64 - 1);
+        }
+        Ok(summary)
+    }
+
+    /// Return the duplicate groups of regular files along with their full hash and the size
+    /// of their files, e.g. to investigate hash collisions or to compare with the output of
+    /// other tools. Symlinks are left out.
+    pub fn duplicates_with_hash(&self) -> Result<Vec<HashedGroup<'_>>> {
+        self.duplicates_with_size()?
+            .into_iter()
+            .map(|(size, paths)| {
+                let file_info = self.get_file_info(self.lookup_file_index(paths[0]).unwrap());
+                let hash = match &file_info.full_hash {
+                    Some(full_hash) => format!("{:x}", full_hash),
+                    None => self
+                        .full_hash_of(file_info)
+                        .map(|hash| format!("{:x}", hash))?,
+                };
+                Ok(HashedGroup {
+                    algorithm: self.hash_algorithm,
+                    hash,
+                    size,
+                    paths,
+                })
+            })
+            .collect()
+    }
+
+    /// Bundle the duplicate groups, the statistics and the skipped files into a `Report`,
+    /// dropping the index.
+    pub fn into_report(self) -> Result<Report> {
+        let mut duplicates: Vec<DuplicateGroup> = self
+            .duplicates_with_size()?
+            .into_iter()
+            .map(|(size, group)| DuplicateGroup {
+                size,
+                paths: sorted_paths(group),
+            })
+            .collect();
+        duplicates.sort_unstable_by(|a, b| a.paths.cmp(&b.paths));
+
+        let mut symlinks: Vec<Vec<PathBuf>> = if self.reports_symlinks() {
+            self.duplicates_symlink().map(sorted_paths).collect()
+        } else {
+            Vec::new()
+        };
+        symlinks.sort_unstable();
+
+        Ok(Report {
+            duplicates,
+            symlinks,
+            stats: self.stats(),
+            full_hash_limit: self.full_hash_limit,
+            skipped: self.ignored_files,
+        })
+    }
+}
+
+/// Return the owned paths of a group, sorted
+fn sorted_paths(group: Vec<&Path>) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = group.into_iter().map(PathBuf::from).collect();
+    paths.sort_unstable();
+    paths
+}
+
+/// Format a size in bytes with a binary unit, e.g. `1.2 MiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Serialize an error kind as its description, e.g. "permission denied"
+pub(crate) fn serialize_error_kind<S: Serializer>(
+    kind: &io::ErrorKind,
+    serializer: S,
+) -> result::Result<S::Ok, S::Error> {
+    serializer.collect_str(kind)
+}
@@ -0,0 +1,128 @@
+use std::io::{self, Read};
+
+use crate::JustOne;
+
+/// A file whose first bytes hold no NUL byte is taken for text, the same way git and diff
+/// tell text from binary
+const TEXT_SNIFF_SIZE: u64 = 8000;
+
+impl JustOne {
+    /// Hash the text files as if their line endings were all LF and their lines had no
+    /// trailing spaces or tabs, so a file checked out with CRLF line endings matches its
+    /// LF copy. A file is taken for text if its first 8000 bytes hold no NUL byte, the
+    /// others are hashed as they are.
+    ///
+    /// The files of different sizes may then be duplicates, so every file goes through the
+    /// small hash instead of only the ones sharing their size, and the size reported for a
+    /// group is the one of its first file. The normalized hashes are not comparable with raw
+    /// ones, e.g. the ones printed by other tools, and the stricter levels still compare the
+    /// raw bytes, so they tell such files apart again.
+    pub fn with_normalize_text(mut self, normalize_text: bool) -> Self {
+        self.normalize_text = normalize_text;
+        self
+    }
+
+    /// Wrap `f` so it yields the content to hash: the normalized text if `normalize_text`
+    /// is on and `f` looks like text, the bytes as they are otherwise
+    pub(crate) fn content_reader<'a, R: Read + 'a>(
+        &self,
+        mut f: R,
+    ) -> io::Result<Box<dyn Read + 'a>> {
+        if !self.normalize_text {
+            return Ok(Box::new(f));
+        }
+        let mut head = Vec::new();
+        (&mut f).take(TEXT_SNIFF_SIZE).read_to_end(&mut head)?;
+        let is_text = !head.contains(&0);
+        let f = io::Cursor::new(head).chain(f);
+        if is_text {
+            Ok(Box::new(NormalizingReader::new(f)))
+        } else {
+            Ok(Box::new(f))
+        }
+    }
+}
+
+/// A reader turning the CRLF and lone CR line endings of a text into LF and dropping the
+/// spaces and tabs at the end of its lines
+struct NormalizingReader<R> {
+    inner: R,
+    /// Bytes read but not normalized yet
+    input: Vec<u8>,
+    /// Normalized bytes not returned yet, from `output_pos`
+    output: Vec<u8>,
+    output_pos: usize,
+    /// Spaces and tabs kept back until it is known whether they end a line
+    pending_blanks: Vec<u8>,
+    /// Whether the last byte was a CR, which ends a line whether a LF follows or not
+    pending_cr: bool,
+    eof: bool,
+}
+
+impl<R: Read> NormalizingReader<R> {
+    fn new(inner: R) -> Self {
+        NormalizingReader {
+            inner,
+            input: vec![0; 8 * 1024],
+            output: Vec::new(),
+            output_pos: 0,
+            pending_blanks: Vec::new(),
+            pending_cr: false,
+            eof: false,
+        }
+    }
+
+    /// Normalize the next chunk of the inner reader into `output`
+    fn fill(&mut self) -> io::Result<()> {
+        self.output.clear();
+        self.output_pos = 0;
+        let read_size = self.inner.read(&mut self.input)?;
+        if read_size == 0 {
+            // the blanks at the end of the text are trailing ones too
+            if self.pending_cr {
+                self.output.push(b'\n');
+            }
+            self.eof = true;
+            return Ok(());
+        }
+        for &byte in &self.input[..read_size] {
+            match byte {
+                b'\r' | b'\n' => {
+                    self.pending_blanks.clear();
+                    if self.pending_cr || byte == b'\n' {
+                        self.output.push(b'\n');
+                    }
+                    self.pending_cr = byte == b'\r';
+                }
+                _ => {
+                    if self.pending_cr {
+                        self.output.push(b'\n');
+                        self.pending_cr = false;
+                    }
+                    if byte == b' ' || byte == b'\t' {
+                        self.pending_blanks.push(byte);
+                    } else {
+                        self.output.append(&mut self.pending_blanks);
+                        self.output.push(byte);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for NormalizingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let len = buf.len().min(self.output.len() - self.output_pos);
+        buf[..len].copy_from_slice(&self.output[self.output_pos..self.output_pos + len]);
+        self.output_pos += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::JustOne;
+
+impl JustOne {
+    /// Read at most about `bytes_per_sec` bytes per second while hashing, all threads
+    /// together, so a scan doesn't starve the other users of a shared disk or NAS. The
+    /// reads sleep as needed once the budget is spent. The traversal only reads the
+    /// metadata of the files and isn't throttled.
+    pub fn with_io_rate_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.io_rate_limit = Some(RateLimiter::new(bytes_per_sec.max(1)));
+        self
+    }
+}
+
+/// A token bucket refilled at `bytes_per_sec`, holding up to one second of reads
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    bytes_per_sec: u64,
+    /// The tokens left, negative when reads are ahead of the budget, and when they were
+    /// last counted
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_sec: u64) -> Self {
+        RateLimiter {
+            bytes_per_sec,
+            bucket: Mutex::new((0.0, Instant::now())),
+        }
+    }
+
+    /// Spend `bytes` tokens, sleeping until the budget covers them
+    pub(crate) fn acquire(&self, bytes: u64) {
+        let rate = self.bytes_per_sec as f64;
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let (tokens, refilled) = &mut *bucket;
+            let now = Instant::now();
+            *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * rate).min(rate);
+            *refilled = now;
+            *tokens -= bytes as f64;
+            if *tokens < 0.0 {
+                Duration::from_secs_f64(-*tokens / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
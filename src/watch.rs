@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::{JustOne, JustOneError, Result, RootIndex};
+
+impl JustOne {
+    /// Watch the scanned folders and keep the index up to date as files are created,
+    /// modified, renamed or deleted, calling `on_change` after each change. Watching stops
+    /// as soon as `on_change` returns false.
+    ///
+    /// A modified file is dropped from the index and scanned again, so its hashes are
+    /// computed anew. The folders of the files given to `update_files` are not watched as a
+    /// whole, only the changes of the files indexed from them are followed.
+    pub fn watch(&mut self, mut on_change: impl FnMut(&JustOne) -> bool) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        // the events come with absolute paths, so the roots are watched by their canonical
+        // path, which the event paths start with
+        let mut watched_roots = Vec::new();
+        for (root, root_dir) in self.roots.iter().enumerate() {
+            let watched_dir = root_dir
+                .canonicalize()
+                .map_err(|e| io_error!(e, root_dir))?;
+            let mode = if self.file_roots.contains(&root) {
+                RecursiveMode::NonRecursive
+            } else {
+                RecursiveMode::Recursive
+            };
+            watcher.watch(&watched_dir, mode)?;
+            watched_roots.push((watched_dir, root));
+        }
+        let registered_files: HashSet<PathBuf> = self
+            .file_infos()
+            .filter(|file_info| self.file_roots.contains(&file_info.root))
+            .map(|file_info| file_info.path.clone())
+            .collect();
+
+        for event in rx {
+            let event = event?;
+            if let EventKind::Access(_) | EventKind::Other | EventKind::Any = event.kind {
+                continue;
+            }
+            for path in &event.paths {
+                self.refresh_path(&watched_roots, &registered_files, path)?;
+            }
+            if !on_change(self) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop everything under the event `path` from the index, then scan it again if it still
+    /// exists. The path is first turned back into a path under the root as it was given to
+    /// `update`, the way it is indexed. Under a root of `update_files`, only the
+    /// `registered_files` are refreshed.
+    fn refresh_path(
+        &mut self,
+        watched_roots: &[(PathBuf, RootIndex)],
+        registered_files: &HashSet<PathBuf>,
+        path: &Path,
+    ) -> Result<()> {
+        let found = watched_roots.iter().find_map(|(watched_dir, root)| {
+            let relative = path.strip_prefix(watched_dir).ok()?;
+            Some((relative, *root))
+        });
+        let (relative, root) = match found {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+        let root_dir = self.roots[root].clone();
+        let path = root_dir.join(relative);
+        if self.file_roots.contains(&root) {
+            if !registered_files.contains(&path) {
+                return Ok(());
+            }
+            self.remove_directory(&path);
+            if path.is_file() {
+                self.update_file(&path)?;
+            }
+            return Ok(());
+        }
+        self.remove_directory(&path);
+        if path.symlink_metadata().is_ok() {
+            self.update_path(&path, &root_dir, root)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<notify::Error> for JustOneError {
+    fn from(err: notify::Error) -> Self {
+        JustOneError::WatchError(err)
+    }
+}
@@ -1,14 +1,21 @@
+#![allow(dead_code)]
+
 use std::env;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
-const TEST_DIR_NAME: &'static str = "test_justone";
+const TEST_DIR_NAME: &str = "test_justone";
 
 fn get_test_dir_path() -> PathBuf {
     env::temp_dir().join(TEST_DIR_NAME)
 }
 
+fn get_named_test_dir_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("{}_{}", TEST_DIR_NAME, name))
+}
+
 /// Remove file, symlink or path(-r)
 fn remove_path(p: impl AsRef<Path>) -> io::Result<()> {
     if p.as_ref().exists() {
@@ -40,4 +47,42 @@ pub fn teardown() -> io::Result<()> {
     remove_path(&test_dir)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Create an empty temp-dir owned by a single test, so tests can run in parallel
+pub fn setup_named(name: &str) -> io::Result<PathBuf> {
+    let test_dir = get_named_test_dir_path(name);
+
+    remove_path(&test_dir)?;
+    fs::create_dir_all(&test_dir)?;
+
+    Ok(test_dir)
+}
+
+// clean the temp-dir created by `setup_named`
+pub fn teardown_named(name: &str) -> io::Result<()> {
+    remove_path(get_named_test_dir_path(name))
+}
+
+/// Create a file (and its parent folders) with the given content
+pub fn create_file(dir: impl AsRef<Path>, name: &str, content: &[u8]) -> io::Result<PathBuf> {
+    let path = dir.as_ref().join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// Run the justone binary with `args` on `folder` and return what it printed, failing with
+/// its error output if it exits with an error
+pub fn run_justone(args: &[&str], folder: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let output = Command::new(env!("CARGO_BIN_EXE_justone"))
+        .args(args)
+        .arg(folder.as_ref())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).into_owned()));
+    }
+    Ok(output.stdout)
+}
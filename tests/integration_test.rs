@@ -1,5 +1,3 @@
-use justone;
-
 mod common;
 
 #[test]
@@ -10,4 +8,2399 @@ fn it_works() -> justone::Result<()> {
     let _dups = jo.update(&test_dir)?.duplicates()?;
     common::teardown()?;
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn tree_fingerprint_matches_identical_trees() -> justone::Result<()> {
+    let test_dir = common::setup_named("tree_fingerprint")?;
+    for tree in &["a", "b", "c"] {
+        common::create_file(&test_dir, &format!("{}/x", tree), b"hello")?;
+        common::create_file(&test_dir, &format!("{}/sub/y", tree), b"world")?;
+    }
+    common::create_file(&test_dir, "c/sub/z", b"world")?;
+
+    let fingerprint = |tree: &str| -> justone::Result<u64> {
+        justone::JustOne::new().update(test_dir.join(tree))?.tree_fingerprint()
+    };
+    let (a, b, c) = (fingerprint("a")?, fingerprint("b")?, fingerprint("c")?);
+    common::teardown_named("tree_fingerprint")?;
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    Ok(())
+}
+
+#[test]
+fn low_memory_drops_unique_sizes() -> justone::Result<()> {
+    let test_dir = common::setup_named("low_memory")?;
+    common::create_file(&test_dir, "empty", b"")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.empty_files().len(), 1);
+    assert_eq!(jo.duplicates()?.len(), 1);
+
+    let mut jo = justone::JustOne::new().with_low_memory(true);
+    jo.update(&test_dir)?;
+    assert!(jo.empty_files().is_empty());
+    assert_eq!(jo.duplicates()?.len(), 1);
+
+    common::teardown_named("low_memory")?;
+    Ok(())
+}
+
+#[test]
+fn empty_files_are_reported_as_their_own_group() -> justone::Result<()> {
+    let test_dir = common::setup_named("empty_distinctly")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    let e1 = common::create_file(&test_dir, "e1", b"")?;
+    let e2 = common::create_file(&test_dir, "e2", b"")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let mut empty_files = jo.empty_files();
+    empty_files.sort();
+    assert_eq!(empty_files, vec![e1.as_path(), e2.as_path()]);
+
+    let output = common::run_justone(&["--include-zero-size-distinctly"], &test_dir)?;
+    let output = String::from_utf8(output).unwrap();
+    common::teardown_named("empty_distinctly")?;
+
+    let (dups, empties) = output.split_once("\n\n").unwrap();
+    assert_eq!(dups, format!("[1] Duplicate found:\n - {}\n - {}", a.display(), b.display()));
+    let mut empties: Vec<&str> = empties.lines().collect();
+    assert_eq!(empties.remove(0), "Empty files (0 bytes):");
+    empties.sort();
+    assert_eq!(empties, vec![format!(" - {}", e1.display()), format!(" - {}", e2.display())]);
+    Ok(())
+}
+
+#[test]
+fn readonly_root_covers_its_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("readonly_root")?;
+    let master = common::create_file(&test_dir, "master/a", b"hello")?;
+    let copy = common::create_file(&test_dir, "copies/a", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.mark_readonly_root(test_dir.join("master"));
+    assert!(jo.is_readonly(&master));
+    assert!(jo.is_readonly(test_dir.join("copies/../master/a")));
+    assert!(!jo.is_readonly(&copy));
+
+    common::teardown_named("readonly_root")?;
+    Ok(())
+}
+
+#[test]
+fn progress_snapshot_counts_work() -> justone::Result<()> {
+    let test_dir = common::setup_named("progress_snapshot")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"unique")?;
+
+    // two passes even on small files, so each pass is counted
+    let mut jo = justone::JustOne::new().with_skip_small_hash(false);
+    let handle = jo.progress_handle();
+    jo.update(&test_dir)?;
+    let snapshot = handle.snapshot();
+    common::teardown_named("progress_snapshot")?;
+
+    assert_eq!(snapshot, jo.progress_snapshot());
+    assert_eq!(snapshot.files_walked, 3);
+    assert_eq!(snapshot.files_hashed, 2);
+    assert_eq!(snapshot.files_pending, 0);
+    assert_eq!(snapshot.bytes_read, 4 * 5);
+    Ok(())
+}
+
+#[test]
+fn parallel_hashing_matches_serial() -> justone::Result<()> {
+    let test_dir = common::setup_named("parallel_hashing")?;
+    for i in 0..20 {
+        let content = format!("content {}", i % 7);
+        common::create_file(&test_dir, &format!("dir{}/f{}", i % 3, i), content.as_bytes())?;
+    }
+
+    let sorted_duplicates = |jo: &mut justone::JustOne| -> justone::Result<Vec<Vec<std::path::PathBuf>>> {
+        let mut dups: Vec<Vec<_>> = jo
+            .update(&test_dir)?
+            .duplicates()?
+            .into_iter()
+            .map(|dup| {
+                let mut dup: Vec<_> = dup.into_iter().map(|p| p.to_owned()).collect();
+                dup.sort();
+                dup
+            })
+            .collect();
+        dups.sort();
+        Ok(dups)
+    };
+    let serial = sorted_duplicates(&mut justone::JustOne::new())?;
+    let parallel = sorted_duplicates(&mut justone::JustOne::new().with_threads(4))?;
+    common::teardown_named("parallel_hashing")?;
+
+    assert_eq!(serial.len(), 7);
+    assert_eq!(serial, parallel);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn duplicate_groups_chain_symlinks_last() -> justone::Result<()> {
+    let test_dir = common::setup_named("duplicate_groups")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    std::os::unix::fs::symlink("a", test_dir.join("link1"))?;
+    std::os::unix::fs::symlink("a", test_dir.join("link2"))?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let groups = jo.duplicate_groups().collect::<justone::Result<Vec<_>>>()?;
+    common::teardown_named("duplicate_groups")?;
+
+    assert_eq!(groups, jo.duplicates()?);
+    assert_eq!(groups.len(), 2);
+    let is_link = |path: &&std::path::Path| path.file_name().unwrap().to_string_lossy().starts_with("link");
+    assert!(groups[1].iter().all(is_link));
+    Ok(())
+}
+
+#[test]
+fn for_each_duplicate_group_streams_the_groups() -> justone::Result<()> {
+    let test_dir = common::setup_named("for_each_duplicate_group")?;
+    common::create_file(&test_dir, "a1", b"hello")?;
+    common::create_file(&test_dir, "a2", b"hello")?;
+    common::create_file(&test_dir, "b1", b"world!")?;
+    common::create_file(&test_dir, "b2", b"world!")?;
+    common::create_file(&test_dir, "c", b"unique")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let mut groups = Vec::new();
+    jo.for_each_duplicate_group(|group| groups.push(group.to_vec()))?;
+    common::teardown_named("for_each_duplicate_group")?;
+
+    assert_eq!(groups, jo.duplicates()?);
+    assert_eq!(groups.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn duplicates_with_size_and_wasted_bytes() -> justone::Result<()> {
+    let test_dir = common::setup_named("wasted_bytes")?;
+    for name in &["a1", "a2", "a3"] {
+        common::create_file(&test_dir, name, b"0123456789")?;
+    }
+    for name in &["b1", "b2"] {
+        common::create_file(&test_dir, name, b"abc")?;
+    }
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let mut groups = jo.duplicates_with_size()?;
+    groups.sort_by_key(|(size, _)| *size);
+    let wasted_bytes = jo.wasted_bytes()?;
+    common::teardown_named("wasted_bytes")?;
+
+    assert_eq!(groups.len(), 2);
+    assert_eq!((groups[0].0, groups[0].1.len()), (3, 2));
+    assert_eq!((groups[1].0, groups[1].1.len()), (10, 3));
+    assert_eq!(wasted_bytes, 3 + 2 * 10);
+    Ok(())
+}
+
+#[test]
+fn excludes_prune_matching_paths() -> justone::Result<()> {
+    let test_dir = common::setup_named("excludes")?;
+    common::create_file(&test_dir, "src/a", b"hello")?;
+    common::create_file(&test_dir, "src/node_modules/a", b"hello")?;
+    common::create_file(&test_dir, "node_modules/a", b"hello")?;
+    common::create_file(&test_dir, "target/debug/a", b"hello")?;
+    common::create_file(&test_dir, "src/b.tmp", b"hello")?;
+    common::create_file(&test_dir, "c", b"hello")?;
+
+    let patterns = vec!["node_modules".to_owned(), "/target".to_owned(), "*.tmp".to_owned()];
+    let mut jo = justone::JustOne::new().with_excludes(patterns)?;
+    let mut dup = jo.update(&test_dir)?.duplicates()?.pop().unwrap();
+    dup.sort();
+    assert_eq!(dup, vec![test_dir.join("c"), test_dir.join("src/a")]);
+
+    common::teardown_named("excludes")?;
+    Ok(())
+}
+
+#[test]
+fn hash_cache_skips_unchanged_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("hash_cache")?;
+    let cache_file = test_dir.join("cache.bin");
+    let files = test_dir.join("files");
+    common::create_file(&files, "a", b"hello")?;
+    let b = common::create_file(&files, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&files)?.save_cache(&cache_file)?;
+    assert!(jo.progress_snapshot().bytes_read > 0);
+
+    let mut jo = justone::JustOne::new();
+    jo.load_cache(&cache_file)?.update(&files)?;
+    assert_eq!(jo.progress_snapshot().bytes_read, 0);
+    assert_eq!(jo.duplicates()?.len(), 1);
+
+    // same size, but a new modified time
+    let modified = std::fs::metadata(&b)?.modified()? + std::time::Duration::from_secs(10);
+    common::create_file(&files, "b", b"world")?;
+    std::fs::File::options().write(true).open(&b)?.set_modified(modified)?;
+
+    let mut jo = justone::JustOne::new();
+    jo.load_cache(&cache_file)?.update(&files)?;
+    assert!(jo.progress_snapshot().bytes_read > 0);
+    assert!(jo.duplicates()?.is_empty());
+
+    common::teardown_named("hash_cache")?;
+    Ok(())
+}
+
+#[test]
+fn hash_cache_discards_other_seed() -> justone::Result<()> {
+    let test_dir = common::setup_named("hash_cache_seed")?;
+    let cache_file = test_dir.join("cache.bin");
+    let files = test_dir.join("files");
+    common::create_file(&files, "a", b"hello")?;
+    common::create_file(&files, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_seed(1);
+    jo.update(&files)?.save_cache(&cache_file)?;
+
+    let mut jo = justone::JustOne::new().with_seed(1);
+    jo.load_cache(&cache_file)?.update(&files)?;
+    assert_eq!(jo.progress_snapshot().bytes_read, 0);
+
+    let mut jo = justone::JustOne::new().with_seed(2);
+    jo.load_cache(&cache_file)?.update(&files)?;
+    assert!(jo.progress_snapshot().bytes_read > 0);
+    assert_eq!(jo.duplicates()?.len(), 1);
+
+    common::teardown_named("hash_cache_seed")?;
+    Ok(())
+}
+
+#[test]
+fn set_progress_replaces_the_progress_observer() -> justone::Result<()> {
+    use justone::{Phase, ProgressObserver};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Counter(Arc<AtomicUsize>);
+
+    impl ProgressObserver for Counter {
+        fn on_phase_start(&self, _phase: Phase, _total: u64) {}
+
+        fn on_item(&self, _phase: Phase) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let test_dir = common::setup_named("set_progress")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+
+    let counter = Counter::default();
+    let count = Arc::clone(&counter.0);
+    let mut jo = justone::JustOne::new().with_progress_observer(counter);
+    jo.update(&test_dir)?;
+    assert!(count.load(Ordering::Relaxed) > 0);
+
+    count.store(0, Ordering::Relaxed);
+    jo.reset().set_progress(false).update(&test_dir)?;
+    assert_eq!(count.load(Ordering::Relaxed), 0);
+
+    let counter = Counter::default();
+    let count = Arc::clone(&counter.0);
+    let mut jo = justone::JustOne::builder()
+        .progress_observer(counter)
+        .show_progress(false)
+        .build();
+    jo.update(&test_dir)?;
+    common::teardown_named("set_progress")?;
+
+    assert_eq!(count.load(Ordering::Relaxed), 0);
+    Ok(())
+}
+
+#[test]
+fn progress_observer_sees_every_phase() -> justone::Result<()> {
+    use justone::{Phase, ProgressObserver};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<(Phase, u64, u64)>>>);
+
+    impl ProgressObserver for Recorder {
+        fn on_phase_start(&self, phase: Phase, total: u64) {
+            self.0.lock().unwrap().push((phase, total, 0));
+        }
+
+        fn on_item(&self, _phase: Phase) {
+            self.0.lock().unwrap().last_mut().unwrap().2 += 1;
+        }
+    }
+
+    let test_dir = common::setup_named("progress_observer")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"world")?;
+    common::create_file(&test_dir, "d", b"unique")?;
+
+    let recorder = Recorder::default();
+    let phases = Arc::clone(&recorder.0);
+    justone::JustOne::new()
+        .with_progress_observer(recorder)
+        .update(&test_dir)?;
+    common::teardown_named("progress_observer")?;
+
+    let expected = vec![
+        // the files are counted as they are found
+        (Phase::Size, 0, 4),
+        (Phase::SmallHash, 3, 3),
+        (Phase::FullHash, 2, 2),
+        (Phase::Merge, 2, 2),
+    ];
+    assert_eq!(*phases.lock().unwrap(), expected);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn hardlinks_are_grouped_separately() -> justone::Result<()> {
+    let test_dir = common::setup_named("hardlinks")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    std::fs::hard_link(&a, test_dir.join("a_link"))?;
+    common::create_file(&test_dir, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let dups = jo.duplicates()?;
+    let mut links = jo.hardlink_groups();
+    common::teardown_named("hardlinks")?;
+
+    assert_eq!(dups.len(), 1);
+    assert_eq!(dups[0].len(), 2);
+    assert_eq!(links.len(), 1);
+    links[0].sort();
+    assert_eq!(links[0], vec![test_dir.join("a"), test_dir.join("a_link")]);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn low_memory_keeps_hardlink_groups() -> justone::Result<()> {
+    let test_dir = common::setup_named("low_memory_hardlinks")?;
+    let a = common::create_file(&test_dir, "a", b"unique size")?;
+    let a_link = test_dir.join("a_link");
+    std::fs::hard_link(&a, &a_link)?;
+    common::create_file(&test_dir, "b", b"alone")?;
+
+    let mut jo = justone::JustOne::new().with_low_memory(true);
+    jo.update(&test_dir)?.update(&test_dir)?;
+    let mut links = jo.hardlink_groups();
+    links[0].sort();
+    assert_eq!(links, vec![vec![a.as_path(), a_link.as_path()]]);
+    assert!(jo.uniques()?.is_empty());
+    assert!(!jo.contains(test_dir.join("b")));
+    assert_eq!(jo.len(), 2);
+
+    common::teardown_named("low_memory_hardlinks")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn report_hardlinks_compares_links_as_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("report_hardlinks")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let a_link = test_dir.join("a_link");
+    std::fs::hard_link(&a, &a_link)?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_report_hardlinks(true);
+    jo.update(&test_dir)?;
+    let dups = jo.duplicates_sorted()?;
+    let links = jo.hardlink_groups();
+    common::teardown_named("report_hardlinks")?;
+
+    assert_eq!(dups, vec![vec![a.as_path(), a_link.as_path(), b.as_path()]]);
+    assert!(links.is_empty());
+    Ok(())
+}
+
+#[test]
+fn remove_duplicates_keeps_one_file() -> justone::Result<()> {
+    let test_dir = common::setup_named("remove_duplicates")?;
+    common::create_file(&test_dir, "a/x", b"hello")?;
+    common::create_file(&test_dir, "a/b/x", b"hello")?;
+    common::create_file(&test_dir, "a/b/c/x", b"hello")?;
+    common::create_file(&test_dir, "a/y", b"unique")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(test_dir.join("a"))?;
+    let summary = jo.remove_duplicates(justone::KeepStrategy::ShortestPath)?;
+
+    assert_eq!(summary.files.len(), 2);
+    assert_eq!(summary.bytes_freed, 10);
+    assert!(test_dir.join("a/x").exists());
+    assert!(!test_dir.join("a/b/x").exists());
+    assert!(!test_dir.join("a/b/c/x").exists());
+    assert!(test_dir.join("a/y").exists());
+
+    common::teardown_named("remove_duplicates")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn link_duplicates_shares_storage() -> justone::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let test_dir = common::setup_named("link_duplicates")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let summary = jo.link_duplicates(justone::KeepStrategy::FirstFound)?;
+
+    assert_eq!(summary.files.len(), 1);
+    assert_eq!(std::fs::metadata(&a)?.ino(), std::fs::metadata(&b)?.ino());
+    assert_eq!(std::fs::read(&b)?, b"hello");
+
+    common::teardown_named("link_duplicates")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn link_duplicates_skips_groups_crossing_devices() -> justone::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let test_dir = common::setup_named("link_cross_device")?;
+    let other_dir = std::path::Path::new("/dev/shm/test_justone_link_cross_device");
+    if !other_dir.parent().unwrap().is_dir()
+        || std::fs::metadata("/dev/shm")?.dev() == std::fs::metadata(&test_dir)?.dev()
+    {
+        // no second filesystem to link across
+        return common::teardown_named("link_cross_device").map_err(Into::into);
+    }
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    let c = common::create_file(other_dir, "c", b"hello")?;
+    let d = common::create_file(&test_dir, "d", b"world")?;
+    let e = common::create_file(&test_dir, "e", b"world")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?.update(other_dir)?;
+    let summary = jo.link_duplicates(justone::KeepStrategy::FirstFound)?;
+
+    // the group spanning both filesystems is left alone, even the files on the same one
+    assert_eq!(summary.files.len(), 1);
+    assert_eq!(std::fs::metadata(&d)?.ino(), std::fs::metadata(&e)?.ino());
+    assert_ne!(std::fs::metadata(&a)?.ino(), std::fs::metadata(&b)?.ino());
+    let mut ignored: Vec<_> = jo.ignored().iter().map(|file| (file.path.clone(), file.kind)).collect();
+    ignored.sort();
+    let kind = std::io::ErrorKind::CrossesDevices;
+    assert_eq!(ignored, vec![(c, kind), (a, kind), (b, kind)]);
+
+    std::fs::remove_dir_all(other_dir)?;
+    common::teardown_named("link_cross_device")?;
+    Ok(())
+}
+
+#[test]
+fn actions_never_touch_readonly_roots() -> justone::Result<()> {
+    let test_dir = common::setup_named("readonly_actions")?;
+    let master = common::create_file(&test_dir, "master/a", b"hello")?;
+    let copy = common::create_file(&test_dir, "copies/a", b"hello")?;
+
+    // the copy is found first, so it would be the one kept
+    let mut jo = justone::JustOne::new();
+    jo.update(test_dir.join("copies"))?.update(test_dir.join("master"))?;
+    jo.mark_readonly_root(test_dir.join("master"));
+    let result = jo.remove_duplicates(justone::KeepStrategy::FirstFound);
+    assert!(matches!(result, Err(justone::JustOneError::ReadOnlyError(_))));
+    assert!(master.exists());
+    assert!(copy.exists());
+
+    let mut jo = justone::JustOne::new();
+    jo.update(test_dir.join("master"))?.update(test_dir.join("copies"))?;
+    jo.mark_readonly_root(test_dir.join("master"));
+    jo.remove_duplicates(justone::KeepStrategy::FirstFound)?;
+    assert!(master.exists());
+    assert!(!copy.exists());
+
+    common::teardown_named("readonly_actions")?;
+    Ok(())
+}
+
+#[test]
+fn canonicalize_dedups_overlapping_roots() -> justone::Result<()> {
+    let test_dir = common::setup_named("canonicalize")?;
+    common::create_file(&test_dir, "sub/a", b"hello")?;
+    common::create_file(&test_dir, "b", b"world")?;
+
+    // the same file reached through two spellings of its folder
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?.update(test_dir.join("sub/../sub"))?;
+    assert_eq!(jo.duplicates()?.len(), 1);
+
+    let mut jo = justone::JustOne::new().with_canonicalize(true);
+    jo.update(&test_dir)?.update(test_dir.join("sub/../sub"))?;
+    assert!(jo.duplicates()?.is_empty());
+
+    common::teardown_named("canonicalize")?;
+    Ok(())
+}
+
+#[test]
+fn depth_limits_traversal() -> justone::Result<()> {
+    let test_dir = common::setup_named("depth")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "sub/a", b"hello")?;
+    common::create_file(&test_dir, "sub/deep/a", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_max_depth(1);
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.is_empty());
+
+    let mut jo = justone::JustOne::new().with_max_depth(2);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    let mut jo = justone::JustOne::new().with_min_depth(2);
+    jo.update(&test_dir)?;
+    let dups = jo.duplicates()?;
+    assert_eq!(dups[0].len(), 2);
+    assert!(!dups[0].contains(&test_dir.join("a").as_path()));
+
+    common::teardown_named("depth")?;
+    Ok(())
+}
+
+#[test]
+fn seed_keeps_duplicates() -> justone::Result<()> {
+    let test_dir = common::setup_named("seed")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"world")?;
+
+    let mut jo = justone::JustOne::new().with_seed(0x5eed);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?.len(), 1);
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    common::teardown_named("seed")?;
+    Ok(())
+}
+
+#[test]
+fn ignored_reports_error_kind() -> justone::Result<()> {
+    let test_dir = common::setup_named("ignored")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    let missing = test_dir.join("missing");
+
+    let mut jo = justone::JustOne::with_config(justone::StrictLevel::Common, true);
+    jo.update(&test_dir)?;
+    assert!(jo.ignored().is_empty());
+    jo.update(&missing)?;
+    assert_eq!(
+        jo.ignored(),
+        &[justone::IgnoredFile {
+            path: missing,
+            kind: std::io::ErrorKind::NotFound,
+        }]
+    );
+
+    common::teardown_named("ignored")?;
+    Ok(())
+}
+
+#[test]
+fn error_policy_collects_errors() -> justone::Result<()> {
+    let test_dir = common::setup_named("error_policy")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    let missing = test_dir.join("missing");
+    let files = vec![a.clone(), missing.clone(), b.clone()];
+
+    let mut jo = justone::JustOne::new();
+    assert!(jo.update_files(files.clone()).is_err());
+
+    let mut jo = justone::JustOne::builder()
+        .error_policy(justone::ErrorPolicy::Collect)
+        .build();
+    jo.update_files(files.clone())?;
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+    assert_eq!(jo.ignored().len(), 1);
+    match jo.errors() {
+        [justone::JustOneError::IOError { files, error }] => {
+            assert_eq!(files, &vec![missing.clone()]);
+            assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        }
+        errors => panic!("unexpected errors {:?}", errors),
+    }
+
+    let mut jo = justone::JustOne::new().with_error_policy(justone::ErrorPolicy::Ignore);
+    jo.update_files(files)?;
+    assert_eq!(jo.ignored().len(), 1);
+    assert!(jo.errors().is_empty());
+
+    common::teardown_named("error_policy")?;
+    Ok(())
+}
+
+#[test]
+fn plan_removals_touches_nothing() -> justone::Result<()> {
+    let test_dir = common::setup_named("plan_removals")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "sub/b", b"hello")?;
+    common::create_file(&test_dir, "c", b"world")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let plan = jo.plan_removals(justone::KeepStrategy::ShortestPath)?;
+
+    assert_eq!(
+        plan,
+        vec![justone::RemovalPlan {
+            kept: a.clone(),
+            remove: vec![b.clone()],
+        }]
+    );
+    assert!(a.exists());
+    assert!(b.exists());
+
+    common::teardown_named("plan_removals")?;
+    Ok(())
+}
+
+#[test]
+fn extensions_filter_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("extensions")?;
+    let a = common::create_file(&test_dir, "a.JPG", b"hello")?;
+    let b = common::create_file(&test_dir, "b.jpg", b"hello")?;
+    common::create_file(&test_dir, "c.txt", b"hello")?;
+    common::create_file(&test_dir, "d", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_extensions(vec!["jpg".to_owned()]);
+    jo.update(&test_dir)?;
+    let mut dups = jo.duplicates()?;
+    assert_eq!(dups.len(), 1);
+    dups[0].sort();
+    assert_eq!(dups[0], vec![a.as_path(), b.as_path()]);
+
+    common::teardown_named("extensions")?;
+    Ok(())
+}
+
+#[test]
+fn stats_accumulate_across_updates() -> justone::Result<()> {
+    let test_dir = common::setup_named("stats")?;
+    common::create_file(&test_dir, "one/a", b"hello")?;
+    common::create_file(&test_dir, "one/b", b"hello")?;
+    common::create_file(&test_dir, "one/c", b"unique content")?;
+    common::create_file(&test_dir, "two/a", b"world")?;
+    common::create_file(&test_dir, "two/b", b"world")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(test_dir.join("one"))?;
+    let stats = jo.stats();
+    assert_eq!(stats.files_seen, 3);
+    assert_eq!(stats.size_candidates, 2);
+    assert_eq!(stats.small_hashes, 2);
+    assert_eq!(stats.full_hashes, 2);
+    assert!(stats.bytes_read >= 10);
+
+    jo.update(test_dir.join("two"))?;
+    let stats = jo.stats();
+    assert_eq!(stats.files_seen, 5);
+    assert_eq!(stats.small_hashes, 4);
+    assert_eq!(stats.full_hashes, 4);
+
+    common::teardown_named("stats")?;
+    Ok(())
+}
+
+#[test]
+fn mmap_matches_buffered_reads() -> justone::Result<()> {
+    let test_dir = common::setup_named("mmap")?;
+    let content = vec![7u8; 100_000];
+    common::create_file(&test_dir, "a", &content)?;
+    common::create_file(&test_dir, "b", &content)?;
+    common::create_file(&test_dir, "c", b"")?;
+    common::create_file(&test_dir, "d", b"")?;
+
+    let mut buffered = justone::JustOne::new();
+    buffered.update(&test_dir)?;
+    let mut mapped = justone::JustOne::new().with_mmap(true);
+    mapped.update(&test_dir)?;
+
+    assert_eq!(buffered.tree_fingerprint()?, mapped.tree_fingerprint()?);
+    assert_eq!(mapped.duplicates()?.len(), 2);
+    assert_eq!(buffered.stats().bytes_read, mapped.stats().bytes_read);
+
+    common::teardown_named("mmap")?;
+    Ok(())
+}
+
+#[test]
+fn duplicates_sorted_is_stable() -> justone::Result<()> {
+    let test_dir = common::setup_named("duplicates_sorted")?;
+    let a1 = common::create_file(&test_dir, "a1", b"hello")?;
+    let a2 = common::create_file(&test_dir, "a2", b"hello")?;
+    let b1 = common::create_file(&test_dir, "b1", b"world!")?;
+    let b2 = common::create_file(&test_dir, "b2", b"world!")?;
+    let b3 = common::create_file(&test_dir, "b3", b"world!")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![
+            vec![a1.as_path(), a2.as_path()],
+            vec![b1.as_path(), b2.as_path(), b3.as_path()],
+        ]
+    );
+
+    common::teardown_named("duplicates_sorted")?;
+    Ok(())
+}
+
+#[test]
+fn builder_configures_options() -> justone::Result<()> {
+    let test_dir = common::setup_named("builder")?;
+    common::create_file(&test_dir, "a.txt", b"hello")?;
+    common::create_file(&test_dir, "b.txt", b"hello")?;
+    common::create_file(&test_dir, "sub/c.txt", b"hello")?;
+    common::create_file(&test_dir, "d.log", b"hello")?;
+
+    let mut jo = justone::JustOne::builder()
+        .strict_level(justone::StrictLevel::ByteByByte)
+        .ignore_error(true)
+        .algorithm(justone::HashAlgorithm::Blake3)
+        .max_depth(1)
+        .extensions(vec!["txt".to_owned()])
+        .excludes(vec!["b.*".to_owned()])?
+        .progress_observer(justone::NoopObserver)
+        .build();
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.is_empty());
+
+    let mut jo = justone::JustOne::builder().max_depth(1).build();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 3);
+
+    common::teardown_named("builder")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_links_survives_loops_and_dangling_links() -> justone::Result<()> {
+    use std::os::unix::fs::symlink;
+
+    let test_dir = common::setup_named("symlink_loops")?;
+    common::create_file(&test_dir, "sub/a", b"hello")?;
+    common::create_file(&test_dir, "b", b"world")?;
+    symlink(&test_dir, test_dir.join("sub/loop"))?;
+    symlink(test_dir.join("sub"), test_dir.join("alias"))?;
+    symlink(test_dir.join("missing"), test_dir.join("dangling"))?;
+
+    let mut jo = justone::JustOne::builder()
+        .follow_links(true)
+        .progress_observer(justone::NoopObserver)
+        .build();
+    jo.update(&test_dir)?;
+
+    // sub/a is reached again through alias but must not be its own duplicate
+    assert!(jo.duplicates()?.is_empty());
+    // the loop is met either as sub/loop or as alias/loop
+    let ignored: Vec<_> = jo.ignored().iter().map(|f| f.path.clone()).collect();
+    assert!(ignored.iter().any(|path| path.ends_with("loop")));
+    assert!(ignored.contains(&test_dir.join("dangling")));
+
+    common::teardown_named("symlink_loops")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_resolve_groups_by_target() -> justone::Result<()> {
+    use std::os::unix::fs::symlink;
+
+    let test_dir = common::setup_named("symlink_resolve")?;
+    common::create_file(&test_dir, "real/x", b"hello")?;
+    symlink("real", test_dir.join("alias"))?;
+    symlink("./real/x", test_dir.join("relative"))?;
+    symlink(test_dir.join("alias/x"), test_dir.join("absolute"))?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.is_empty());
+
+    let mut jo = justone::JustOne::new().with_symlink_resolve(true);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    common::teardown_named("symlink_resolve")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_grouped_by_target_from_their_folder() -> justone::Result<()> {
+    use std::os::unix::fs::symlink;
+
+    let test_dir = common::setup_named("symlink_lexical")?;
+    common::create_file(&test_dir, "x", b"hello")?;
+    common::create_file(&test_dir, "b/c/y", b"world")?;
+    std::fs::create_dir_all(test_dir.join("a"))?;
+    symlink("../x", test_dir.join("a/link"))?;
+    symlink("../x", test_dir.join("b/c/link"))?;
+    symlink("./x", test_dir.join("link"))?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let mut dups = jo.duplicates_owned()?;
+    common::teardown_named("symlink_lexical")?;
+
+    assert_eq!(dups.len(), 1);
+    dups[0].sort();
+    assert_eq!(dups[0], vec![test_dir.join("a/link"), test_dir.join("link")]);
+    Ok(())
+}
+
+#[test]
+fn remove_directory_and_file_update_the_index() -> justone::Result<()> {
+    let test_dir = common::setup_named("remove_from_index")?;
+    let a = common::create_file(&test_dir, "one/a", b"hello")?;
+    common::create_file(&test_dir, "two/a", b"hello")?;
+    let c = common::create_file(&test_dir, "two/c", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 3);
+
+    assert!(jo.remove_file(&c));
+    assert!(!jo.remove_file(&c));
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    assert_eq!(jo.remove_directory(test_dir.join("two")), 1);
+    assert!(jo.duplicates()?.is_empty());
+
+    // scanning the folder again brings its files back
+    jo.update(test_dir.join("two"))?;
+    let dups = jo.duplicates()?;
+    assert_eq!(dups[0].len(), 3);
+    assert!(dups[0].contains(&a.as_path()));
+
+    common::teardown_named("remove_from_index")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn remove_file_keeps_other_hardlinks() -> justone::Result<()> {
+    let test_dir = common::setup_named("remove_hardlink")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    std::fs::hard_link(&a, test_dir.join("b"))?;
+    common::create_file(&test_dir, "c", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let first = jo.hardlink_groups()[0][0].to_owned();
+    assert!(jo.remove_file(&first));
+
+    // the other link now stands for the shared content
+    assert!(jo.hardlink_groups().is_empty());
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    common::teardown_named("remove_hardlink")?;
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn watch_follows_file_changes() -> justone::Result<()> {
+    use std::{thread, time::Duration};
+
+    let test_dir = common::setup_named("watch")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"world")?;
+
+    let mut jo = justone::JustOne::new().with_progress_observer(justone::NoopObserver);
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.is_empty());
+
+    let writer = {
+        let b = b.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            std::fs::write(&b, b"hello").unwrap();
+        })
+    };
+    jo.watch(|jo| jo.duplicates().unwrap().is_empty())?;
+    writer.join().unwrap();
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    common::teardown_named("watch")?;
+    Ok(())
+}
+
+#[cfg(all(unix, feature = "watch"))]
+#[test]
+fn watch_follows_relative_roots() -> justone::Result<()> {
+    use std::{path::PathBuf, thread, time::Duration};
+
+    let test_dir = common::setup_named("watch_relative")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    // the same folder, relative to the current one
+    let mut relative_dir = PathBuf::new();
+    for _ in std::env::current_dir()?.components().skip(1) {
+        relative_dir.push("..");
+    }
+    relative_dir.push(test_dir.strip_prefix("/").unwrap());
+
+    let mut jo = justone::JustOne::new().with_progress_observer(justone::NoopObserver);
+    jo.update(&relative_dir)?;
+    assert_eq!(jo.len(), 1);
+
+    let writer = {
+        let test_dir = test_dir.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            common::create_file(&test_dir, "b", b"hello").unwrap();
+        })
+    };
+    jo.watch(|jo| jo.duplicates().unwrap().is_empty())?;
+    writer.join().unwrap();
+    assert_eq!(jo.len(), 2);
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![vec![relative_dir.join("a"), relative_dir.join("b")]]
+    );
+
+    common::teardown_named("watch_relative")?;
+    Ok(())
+}
+
+#[cfg(all(unix, feature = "watch"))]
+#[test]
+fn watch_follows_only_the_registered_files() -> justone::Result<()> {
+    use std::{thread, time::Duration};
+
+    let test_dir = common::setup_named("watch_files")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"world")?;
+
+    let mut jo = justone::JustOne::new().with_progress_observer(justone::NoopObserver);
+    jo.update_files(vec![a.clone(), b.clone()])?;
+    assert!(jo.duplicates()?.is_empty());
+
+    let writer = {
+        let (test_dir, b) = (test_dir.clone(), b.clone());
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            common::create_file(&test_dir, "c", b"hello").unwrap();
+            common::create_file(&test_dir, "sub/d", b"hello").unwrap();
+            std::fs::write(&b, b"hello").unwrap();
+        })
+    };
+    jo.watch(|jo| jo.duplicates().unwrap().is_empty())?;
+    writer.join().unwrap();
+    assert_eq!(jo.len(), 2);
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![a.as_path(), b.as_path()]]);
+
+    common::teardown_named("watch_files")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn html_format_escapes_paths_and_orders_by_wasted_bytes() -> justone::Result<()> {
+    let test_dir = common::setup_named("html_format")?;
+    // 3 files of 4 bytes waste 8 bytes, more than 2 files of 6 bytes
+    common::create_file(&test_dir, "small1", b"tiny")?;
+    common::create_file(&test_dir, "small2", b"tiny")?;
+    common::create_file(&test_dir, "small3", b"tiny")?;
+    common::create_file(&test_dir, "large1", b"larger")?;
+    let odd = common::create_file(&test_dir, "<&\"'>", b"larger")?;
+
+    let output = common::run_justone(&["--format", "html"], &test_dir)?;
+    let output = String::from_utf8(output).unwrap();
+    common::teardown_named("html_format")?;
+
+    let escaped = format!("{}/&lt;&amp;&quot;&#39;&gt;", test_dir.display());
+    assert!(output.contains(&format!("<code>{}</code>", escaped)));
+    assert!(!output.contains(&odd.display().to_string()));
+    let small = output.find("<summary>3 files, 4 B each</summary>").unwrap();
+    let large = output.find("<summary>2 files, 6 B each</summary>").unwrap();
+    assert!(small < large);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn csv_format_writes_a_row_per_file() -> justone::Result<()> {
+    let test_dir = common::setup_named("csv_format")?;
+    common::create_file(&test_dir, "a,b", b"hello")?;
+    common::create_file(&test_dir, "c", b"hello")?;
+    common::create_file(&test_dir, "d", b"world")?;
+    common::create_file(&test_dir, "e", b"world")?;
+
+    let output = common::run_justone(&["--format", "csv", "--algorithm", "sha256"], &test_dir)?;
+    let output = String::from_utf8(output).unwrap();
+    common::teardown_named("csv_format")?;
+
+    let hello = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    let world = "sha256:486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+    let dir = test_dir.display();
+    let expected = format!(
+        "group_id,path,size_bytes,hash\n\
+         1,\"{dir}/a,b\",5,{hello}\n\
+         1,{dir}/c,5,{hello}\n\
+         2,{dir}/d,5,{world}\n\
+         2,{dir}/e,5,{world}\n",
+        dir = dir,
+        hello = hello,
+        world = world
+    );
+    assert_eq!(output, expected);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn print0_frames_paths_and_groups_with_nul() -> justone::Result<()> {
+    let test_dir = common::setup_named("print0")?;
+    let a1 = common::create_file(&test_dir, "a1", b"hello")?;
+    let a2 = common::create_file(&test_dir, "a 2\nnewline", b"hello")?;
+    let b1 = common::create_file(&test_dir, "b1", b"world")?;
+    let b2 = common::create_file(&test_dir, "b2", b"world")?;
+    let c = common::create_file(&test_dir, "c", b"unique")?;
+    let d = common::create_file(&test_dir, "d", b"single")?;
+
+    let dups = common::run_justone(&["--print0"], &test_dir)?;
+    let uniques = common::run_justone(&["--uniques", "--print0"], &test_dir)?;
+    common::teardown_named("print0")?;
+
+    let framed = |paths: &[&std::path::Path]| -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for path in paths {
+            bytes.extend_from_slice(path.as_os_str().as_encoded_bytes());
+            bytes.push(0);
+        }
+        bytes
+    };
+    // the groups are separated by an extra NUL, with none after the last one
+    let mut expected = framed(&[&a2, &a1]);
+    expected.push(0);
+    expected.extend(framed(&[&b1, &b2]));
+    assert_eq!(dups, expected);
+    assert_eq!(uniques, framed(&[&c, &d]));
+    Ok(())
+}
+
+#[test]
+fn time_is_printed_apart_from_the_results() -> justone::Result<()> {
+    let test_dir = common::setup_named("time_stderr")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+
+    let text = common::run_justone(&["--time"], &test_dir)?;
+    let dry_run = common::run_justone(&["--time", "--dry-run"], &test_dir)?;
+    common::teardown_named("time_stderr")?;
+
+    for output in [text, dry_run] {
+        let output = String::from_utf8(output).unwrap();
+        assert!(!output.is_empty());
+        assert!(!output.contains("Time Waste"));
+    }
+    Ok(())
+}
+
+#[test]
+fn find_duplicates_of_leaves_the_index_alone() -> justone::Result<()> {
+    let test_dir = common::setup_named("find_duplicates_of")?;
+    let a = common::create_file(&test_dir, "indexed/a", b"hello")?;
+    let b = common::create_file(&test_dir, "indexed/b", b"world")?;
+    let candidate = common::create_file(&test_dir, "candidate", b"hello")?;
+    let other = common::create_file(&test_dir, "other", b"hellp")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(test_dir.join("indexed"))?;
+
+    assert_eq!(jo.find_duplicates_of(&candidate)?, vec![a.as_path()]);
+    assert!(jo.find_duplicates_of(&other)?.is_empty());
+    // an indexed file is not its own duplicate
+    assert!(jo.find_duplicates_of(&b)?.is_empty());
+    assert!(jo.duplicates()?.is_empty());
+
+    common::teardown_named("find_duplicates_of")?;
+    Ok(())
+}
+
+#[test]
+fn respect_gitignore_prunes_ignored_paths() -> justone::Result<()> {
+    let test_dir = common::setup_named("gitignore")?;
+    common::create_file(&test_dir, ".gitignore", b"target/\n*.log\n")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "target/a", b"hello")?;
+    common::create_file(&test_dir, "b.log", b"hello")?;
+    common::create_file(&test_dir, "sub/.ignore", b"!keep.log\nc\n")?;
+    common::create_file(&test_dir, "sub/keep.log", b"hello")?;
+    common::create_file(&test_dir, "sub/c", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_respect_gitignore(true);
+    jo.update(&test_dir)?;
+    let dups = jo.duplicates_sorted()?;
+    assert_eq!(
+        dups,
+        vec![vec![
+            test_dir.join("a").as_path(),
+            test_dir.join("sub/keep.log").as_path(),
+        ]]
+    );
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 5);
+
+    common::teardown_named("gitignore")?;
+    Ok(())
+}
+
+/// A hasher making every file of the same size collide
+struct ConstHasher;
+
+impl std::hash::Hasher for ConstHasher {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+fn colliding_hasher_creator() -> justone::HasherCreator {
+    Box::new(|| Box::new(ConstHasher))
+}
+
+#[test]
+fn common_verified_splits_collisions() -> justone::Result<()> {
+    let test_dir = common::setup_named("common_verified")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"world")?;
+
+    let mut jo = justone::JustOne::with_full_config(
+        colliding_hasher_creator(),
+        justone::StrictLevel::Common,
+        false,
+    );
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 3);
+
+    let mut jo = justone::JustOne::with_full_config(
+        colliding_hasher_creator(),
+        justone::StrictLevel::CommonVerified,
+        false,
+    );
+    jo.update(&test_dir)?;
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![vec![a.as_path(), b.as_path()]]
+    );
+
+    common::teardown_named("common_verified")?;
+    Ok(())
+}
+
+#[test]
+fn actions_never_trust_colliding_hashes() -> justone::Result<()> {
+    let test_dir = common::setup_named("action_collisions")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"world")?;
+    let c = common::create_file(&test_dir, "c", b"hello")?;
+
+    let mut jo = justone::JustOne::with_full_config(
+        colliding_hasher_creator(),
+        justone::StrictLevel::Common,
+        false,
+    );
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 3);
+    let summary = jo.remove_duplicates(justone::KeepStrategy::FirstAlphabetical)?;
+    assert_eq!(summary.files, vec![c.clone()]);
+    assert!(a.exists());
+    assert!(b.exists());
+    assert!(!c.exists());
+
+    common::teardown_named("action_collisions")?;
+    Ok(())
+}
+
+#[test]
+fn strict_levels_group_identical_files_once() -> justone::Result<()> {
+    let test_dir = common::setup_named("strict_groups")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    let c = common::create_file(&test_dir, "c", b"hello")?;
+
+    for strict_level in [justone::StrictLevel::Shallow, justone::StrictLevel::ByteByByte] {
+        let mut jo = justone::JustOne::with_config(strict_level, false);
+        jo.update(&test_dir)?;
+        assert_eq!(
+            jo.duplicates_sorted()?,
+            vec![vec![a.as_path(), b.as_path(), c.as_path()]]
+        );
+    }
+
+    common::teardown_named("strict_groups")?;
+    Ok(())
+}
+
+#[test]
+fn strict_levels_split_collisions() -> justone::Result<()> {
+    let test_dir = common::setup_named("strict_collisions")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"world")?;
+
+    // a shallow comparison can't tell apart files of the same size and mtime
+    let mut jo = justone::JustOne::with_full_config(
+        colliding_hasher_creator(),
+        justone::StrictLevel::ByteByByte,
+        false,
+    );
+    jo.update(&test_dir)?;
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![vec![a.as_path(), b.as_path()]]
+    );
+
+    common::teardown_named("strict_collisions")?;
+    Ok(())
+}
+
+#[test]
+fn small_hash_size_tells_apart_shared_headers() -> justone::Result<()> {
+    let test_dir = common::setup_named("small_hash_size")?;
+    let mut a = vec![0u8; 2000];
+    let mut b = a.clone();
+    a[1500] = 1;
+    b[1500] = 2;
+    common::create_file(&test_dir, "a", &a)?;
+    common::create_file(&test_dir, "b", &b)?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.is_empty());
+    assert_eq!(jo.stats().full_hashes, 2);
+
+    let mut jo = justone::JustOne::new()
+        .with_small_hash_size(2048)
+        .with_skip_small_hash(false);
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.is_empty());
+    assert_eq!(jo.stats().full_hashes, 0);
+
+    common::teardown_named("small_hash_size")?;
+    Ok(())
+}
+
+#[test]
+fn ignore_empty_skips_zero_byte_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("ignore_empty")?;
+    common::create_file(&test_dir, "a", b"")?;
+    common::create_file(&test_dir, "b", b"")?;
+    common::create_file(&test_dir, "c", b"hello")?;
+    common::create_file(&test_dir, "d", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?.len(), 2);
+
+    let mut jo = justone::JustOne::new().with_ignore_empty(true);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?.len(), 1);
+    assert!(jo.empty_files().is_empty());
+
+    common::teardown_named("ignore_empty")?;
+    Ok(())
+}
+
+#[test]
+fn progress_observer_counts_full_hash_bytes() -> justone::Result<()> {
+    use justone::{Phase, ProgressObserver};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Bytes {
+        total: AtomicU64,
+        read: AtomicU64,
+    }
+    struct ByteRecorder(Arc<Bytes>);
+
+    impl ProgressObserver for ByteRecorder {
+        fn on_phase_start(&self, _phase: Phase, _total: u64) {}
+
+        fn on_item(&self, _phase: Phase) {}
+
+        fn on_bytes_start(&self, phase: Phase, total_bytes: u64) {
+            assert_eq!(phase, Phase::FullHash);
+            self.0.total.store(total_bytes, Ordering::Relaxed);
+        }
+
+        fn on_bytes(&self, phase: Phase, bytes: u64) {
+            if phase == Phase::FullHash {
+                self.0.read.fetch_add(bytes, Ordering::Relaxed);
+            }
+        }
+    }
+
+    let test_dir = common::setup_named("progress_bytes")?;
+    common::create_file(&test_dir, "a", b"hello world")?;
+    common::create_file(&test_dir, "b", b"hello world")?;
+    common::create_file(&test_dir, "c", b"unique")?;
+
+    let bytes = Arc::new(Bytes::default());
+    let mut jo = justone::JustOne::new()
+        .with_skip_small_hash(false)
+        .with_progress_observer(ByteRecorder(bytes.clone()));
+    jo.update(&test_dir)?;
+
+    assert_eq!(bytes.total.load(Ordering::Relaxed), 22);
+    assert_eq!(bytes.read.load(Ordering::Relaxed), 22);
+
+    common::teardown_named("progress_bytes")?;
+    Ok(())
+}
+
+#[test]
+fn cancel_token_stops_update() -> justone::Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let test_dir = common::setup_named("cancel")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+
+    let cancel_token = Arc::new(AtomicBool::new(true));
+    let mut jo = justone::JustOne::new().with_cancel_token(Arc::clone(&cancel_token));
+    let result = jo.update(&test_dir).map(|_| ());
+    assert!(matches!(result, Err(justone::JustOneError::Cancelled)));
+    assert!(jo.duplicates()?.is_empty());
+
+    // the same folder is scanned completely once the token is reset
+    cancel_token.store(false, Ordering::Relaxed);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    common::teardown_named("cancel")?;
+    Ok(())
+}
+
+#[test]
+fn duplicates_owned_outlives_index() -> justone::Result<()> {
+    let test_dir = common::setup_named("owned")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let expected: Vec<Vec<std::path::PathBuf>> = jo
+        .duplicates()?
+        .into_iter()
+        .map(|group| group.into_iter().map(|path| path.to_owned()).collect())
+        .collect();
+    let dups = jo.duplicates_owned()?;
+    drop(jo);
+    assert_eq!(dups, expected);
+    assert_eq!(dups[0].len(), 2);
+
+    common::teardown_named("owned")?;
+    Ok(())
+}
+
+#[test]
+fn update_all_across_roots() -> justone::Result<()> {
+    let test_dir = common::setup_named("update_all")?;
+    let dir_a = test_dir.join("a");
+    let dir_b = test_dir.join("b");
+    std::fs::create_dir_all(&dir_a)?;
+    std::fs::create_dir_all(&dir_b)?;
+    common::create_file(&dir_a, "x", b"same content")?;
+    common::create_file(&dir_b, "y", b"same content")?;
+    common::create_file(&dir_b, "z", b"other content")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update_all(&[&dir_a, &dir_b])?;
+    let mut sequential = justone::JustOne::new();
+    sequential.update(&dir_a)?.update(&dir_b)?;
+    assert_eq!(jo.duplicates_sorted()?, sequential.duplicates_sorted()?);
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![vec![dir_a.join("x").as_path(), dir_b.join("y").as_path()]]
+    );
+
+    // overlapping roots find the same files twice, which are not duplicates of themselves
+    let mut jo = justone::JustOne::new();
+    jo.update_all(&[&test_dir, &dir_b, &dir_b])?;
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![vec![dir_a.join("x").as_path(), dir_b.join("y").as_path()]]
+    );
+
+    common::teardown_named("update_all")?;
+    Ok(())
+}
+
+#[test]
+fn require_same_name_splits_groups() -> justone::Result<()> {
+    let test_dir = common::setup_named("same_name")?;
+    let dir_a = test_dir.join("a");
+    let dir_b = test_dir.join("b");
+    std::fs::create_dir_all(&dir_a)?;
+    std::fs::create_dir_all(&dir_b)?;
+    common::create_file(&dir_a, "photo.jpg", b"same content")?;
+    common::create_file(&dir_b, "photo.jpg", b"same content")?;
+    common::create_file(&dir_b, "copy.jpg", b"same content")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 3);
+
+    let mut jo = justone::JustOne::new().with_require_same_name(true);
+    jo.update(&test_dir)?;
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![vec![
+            dir_a.join("photo.jpg").as_path(),
+            dir_b.join("photo.jpg").as_path()
+        ]]
+    );
+    assert_eq!(jo.find_duplicates_of(dir_b.join("copy.jpg"))?.len(), 0);
+    assert_eq!(
+        jo.find_duplicates_of(dir_a.join("photo.jpg"))?,
+        vec![dir_b.join("photo.jpg").as_path()]
+    );
+
+    common::teardown_named("same_name")?;
+    Ok(())
+}
+
+#[test]
+fn scan_sends_events() -> justone::Result<()> {
+    use justone::{Event, Phase};
+
+    let test_dir = common::setup_named("events")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"world!")?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut jo = justone::JustOne::new()
+        .with_progress_observer(justone::NoopObserver)
+        .with_event_sender(tx);
+    let dups = jo.scan(&test_dir)?;
+    drop(jo);
+    let events: Vec<Event> = rx.iter().collect();
+
+    assert_eq!(events[0], Event::PhaseStarted(Phase::Size));
+    let found = events
+        .iter()
+        .filter(|event| matches!(event, Event::FileFound { .. }))
+        .count();
+    assert_eq!(found, 3);
+    assert!(events.contains(&Event::FileFound {
+        path: test_dir.join("c"),
+        size: 6
+    }));
+    let hashed = events
+        .iter()
+        .filter(|event| matches!(event, Event::FileHashed { size: 5, .. }))
+        .count();
+    assert_eq!(hashed, 2);
+    assert!(events.contains(&Event::DuplicateGroupFound {
+        paths: dups[0].clone()
+    }));
+    assert!(matches!(events.last(), Some(Event::Finished { stats }) if stats.full_hashes == 2));
+
+    common::teardown_named("events")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn file_found_is_sent_once_per_file() -> justone::Result<()> {
+    use justone::Event;
+
+    let test_dir = common::setup_named("file_found_once")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"world")?;
+    std::fs::hard_link(&a, test_dir.join("link"))?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut jo = justone::JustOne::new().with_event_sender(tx);
+    jo.update(&test_dir)?.update(&test_dir)?;
+    drop(jo);
+    common::teardown_named("file_found_once")?;
+
+    let found: Vec<_> = rx
+        .iter()
+        .filter_map(|event| match event {
+            Event::FileFound { path, .. } => path.file_name().map(|name| name.to_owned()),
+            _ => None,
+        })
+        .collect();
+    // one of `a` and `link` stands for the file, the other is its secondary hardlink
+    assert_eq!(found.len(), 2);
+    assert!(found.contains(&"b".into()));
+    Ok(())
+}
+
+#[test]
+fn into_report_bundles_results() -> justone::Result<()> {
+    let test_dir = common::setup_named("report")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "c", b"world!")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let stats = jo.stats();
+    let report = std::thread::spawn(move || jo.into_report()).join().unwrap()?;
+    assert_eq!(
+        report.duplicates,
+        vec![justone::DuplicateGroup {
+            size: 5,
+            paths: vec![test_dir.join("a"), test_dir.join("b")],
+        }]
+    );
+    assert!(report.symlinks.is_empty());
+    assert!(report.skipped.is_empty());
+    assert_eq!(report.stats, stats);
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["duplicates"][0]["size"], 5);
+    assert_eq!(json["stats"]["files_seen"], 3);
+
+    common::teardown_named("report")?;
+    Ok(())
+}
+
+#[cfg(feature = "perceptual")]
+#[test]
+fn duplicates_perceptual_matches_resized_images() -> justone::Result<()> {
+    let test_dir = common::setup_named("perceptual")?;
+    let gradient = image::GrayImage::from_fn(64, 64, |x, y| image::Luma([(x * 3 + y) as u8]));
+    let inverse = image::GrayImage::from_fn(64, 64, |x, y| image::Luma([255 - (x * 3 + y) as u8]));
+    let resized = image::imageops::resize(&gradient, 32, 32, image::imageops::FilterType::Triangle);
+    gradient.save(test_dir.join("photo.png")).unwrap();
+    resized.save(test_dir.join("photo_small.png")).unwrap();
+    inverse.save(test_dir.join("other.png")).unwrap();
+    common::create_file(&test_dir, "a.txt", b"hello")?;
+    common::create_file(&test_dir, "b.txt", b"hello")?;
+    common::create_file(&test_dir, "fake.png", b"not an image")?;
+    common::create_file(&test_dir, "fake_copy.png", b"not an image")?;
+
+    let mut jo = justone::JustOne::new().with_perceptual_distance(4);
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.iter().all(|group| group.len() == 2));
+    assert_eq!(
+        jo.duplicates_perceptual()?,
+        vec![
+            vec![test_dir.join("a.txt").as_path(), test_dir.join("b.txt").as_path()],
+            vec![test_dir.join("fake.png").as_path(), test_dir.join("fake_copy.png").as_path()],
+            vec![test_dir.join("photo.png").as_path(), test_dir.join("photo_small.png").as_path()],
+        ]
+    );
+
+    common::teardown_named("perceptual")?;
+    Ok(())
+}
+
+#[test]
+fn file_times_sort_group_by_age() -> justone::Result<()> {
+    use std::time::{Duration, SystemTime};
+
+    let test_dir = common::setup_named("file_times")?;
+    common::create_file(&test_dir, "copy", b"hello")?;
+    common::create_file(&test_dir, "original", b"hello")?;
+    let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(test_dir.join("original"))?
+        .set_modified(old)?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let times = jo.file_times(test_dir.join("original")).unwrap();
+    assert_eq!(times.modified, Some(old));
+    assert!(jo.file_times(test_dir.join("missing")).is_none());
+
+    let mut group = jo.duplicates()?.remove(0);
+    group.sort_by_key(|path| jo.file_times(path).and_then(|times| times.modified));
+    assert_eq!(group[0], test_dir.join("original"));
+
+    common::teardown_named("file_times")?;
+    Ok(())
+}
+
+#[test]
+fn same_filesystem_keeps_local_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("same_filesystem")?;
+    let sub_dir = test_dir.join("sub");
+    std::fs::create_dir_all(&sub_dir)?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&sub_dir, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_same_filesystem(true);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 2);
+
+    common::teardown_named("same_filesystem")?;
+    Ok(())
+}
+
+#[test]
+fn full_hash_limit_reports_candidates() -> justone::Result<()> {
+    let test_dir = common::setup_named("full_hash_limit")?;
+    let mut a = vec![7u8; 4096];
+    let mut b = a.clone();
+    a[3000] = 1;
+    b[3000] = 2;
+    common::create_file(&test_dir, "a", &a)?;
+    common::create_file(&test_dir, "b", &b)?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert!(!jo.reports_candidates());
+    assert!(jo.duplicates()?.is_empty());
+
+    for &mmap in &[false, true] {
+        let mut jo = justone::JustOne::new()
+            .with_full_hash_limit(2048)
+            .with_mmap(mmap);
+        jo.update(&test_dir)?;
+        assert!(jo.reports_candidates());
+        assert_eq!(jo.duplicates()?[0].len(), 2);
+        assert_eq!(jo.stats().bytes_read, 2 * (1024 + 2048));
+        assert_eq!(jo.into_report()?.full_hash_limit, Some(2048));
+    }
+
+    common::teardown_named("full_hash_limit")?;
+    Ok(())
+}
+
+#[test]
+fn full_hash_limit_actions_confirm_candidates() -> justone::Result<()> {
+    let test_dir = common::setup_named("full_hash_limit_actions")?;
+    let a = common::create_file(&test_dir, "a", b"prefix--tail one")?;
+    let b = common::create_file(&test_dir, "b", b"prefix--tail two")?;
+    let c = common::create_file(&test_dir, "c", b"prefix--tail one")?;
+
+    let mut jo = justone::JustOne::new()
+        .with_small_hash_size(4)
+        .with_full_hash_limit(8);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates()?[0].len(), 3);
+    let plans = jo.plan_removals(justone::KeepStrategy::FirstAlphabetical)?;
+    assert_eq!(plans.len(), 1);
+    assert_eq!(plans[0].remove, vec![c.clone()]);
+
+    let summary = jo.remove_duplicates(justone::KeepStrategy::FirstAlphabetical)?;
+    assert_eq!(summary.files, vec![c.clone()]);
+    assert!(a.exists());
+    assert!(b.exists());
+    assert!(!c.exists());
+
+    common::teardown_named("full_hash_limit_actions")?;
+    Ok(())
+}
+
+#[test]
+fn repeated_and_overlapping_updates_match_single_scan() -> justone::Result<()> {
+    let test_dir = common::setup_named("overlapping_updates")?;
+    let sub_dir = test_dir.join("sub");
+    std::fs::create_dir_all(&sub_dir)?;
+    common::create_file(&test_dir, "a", b"same content")?;
+    common::create_file(&sub_dir, "b", b"same content")?;
+    common::create_file(&sub_dir, "c", b"same content")?;
+    common::create_file(&test_dir, "d", b"same prefix 1")?;
+    common::create_file(&sub_dir, "e", b"same prefix 2")?;
+    common::create_file(&sub_dir, "f", b"unique")?;
+    common::create_file(&test_dir, "g", b"other pair")?;
+    common::create_file(&sub_dir, "h", b"other pair")?;
+    std::fs::hard_link(sub_dir.join("b"), sub_dir.join("b_link"))?;
+
+    for level in 0..4 {
+        let new = || {
+            let strict_level = match level {
+                0 => justone::StrictLevel::Common,
+                1 => justone::StrictLevel::CommonVerified,
+                2 => justone::StrictLevel::Shallow,
+                _ => justone::StrictLevel::ByteByByte,
+            };
+            justone::JustOne::with_config(strict_level, false).with_low_memory(level % 2 == 1)
+        };
+        let mut once = new();
+        once.update(&test_dir)?;
+        let expected = once.duplicates_sorted()?;
+        assert_eq!(expected.len(), 2);
+
+        let mut twice = new();
+        twice.update(&test_dir)?.update(&test_dir)?;
+        assert_eq!(twice.duplicates_sorted()?, expected);
+
+        let mut dir_then_sub = new();
+        dir_then_sub.update(&test_dir)?.update(&sub_dir)?;
+        assert_eq!(dir_then_sub.duplicates_sorted()?, expected);
+
+        let mut sub_then_dir = new();
+        sub_then_dir.update(&sub_dir)?.update(&test_dir)?;
+        assert_eq!(sub_then_dir.duplicates_sorted()?, expected);
+
+        let mut sub_twice_then_dir = new();
+        sub_twice_then_dir
+            .update(&sub_dir)?
+            .update(&sub_dir)?
+            .update(&test_dir)?
+            .update(&sub_dir)?;
+        assert_eq!(sub_twice_then_dir.duplicates_sorted()?, expected);
+    }
+
+    common::teardown_named("overlapping_updates")?;
+    Ok(())
+}
+
+#[test]
+fn update_after_cancelled_update_finds_all_duplicates() -> justone::Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct CancelOnSmallHash(Arc<AtomicBool>);
+
+    impl justone::ProgressObserver for CancelOnSmallHash {
+        fn on_phase_start(&self, phase: justone::Phase, _total: u64) {
+            if phase == justone::Phase::SmallHash {
+                self.0.store(true, Ordering::Relaxed);
+            }
+        }
+
+        fn on_item(&self, _phase: justone::Phase) {}
+    }
+
+    let test_dir = common::setup_named("update_after_cancel")?;
+    let dir_a = test_dir.join("a");
+    let dir_b = test_dir.join("b");
+    std::fs::create_dir_all(&dir_a)?;
+    std::fs::create_dir_all(&dir_b)?;
+    common::create_file(&dir_a, "x", b"same content")?;
+    common::create_file(&dir_a, "y", b"same content")?;
+    common::create_file(&dir_b, "z", b"same content")?;
+
+    let cancel_token = Arc::new(AtomicBool::new(false));
+    let mut jo = justone::JustOne::new()
+        .with_cancel_token(Arc::clone(&cancel_token))
+        .with_progress_observer(CancelOnSmallHash(Arc::clone(&cancel_token)));
+    let result = jo.update(&dir_a).map(|_| ());
+    assert!(matches!(result, Err(justone::JustOneError::Cancelled)));
+    assert!(jo.duplicates()?.is_empty());
+
+    // the files left unhashed by the cancelled pass are hashed along with the new one
+    let mut jo = jo.with_progress_observer(justone::NoopObserver);
+    cancel_token.store(false, Ordering::Relaxed);
+    jo.update(&dir_b)?;
+    assert_eq!(jo.duplicates()?[0].len(), 3);
+
+    common::teardown_named("update_after_cancel")?;
+    Ok(())
+}
+
+#[test]
+fn reset_then_scan_matches_fresh_instance() -> justone::Result<()> {
+    let test_dir = common::setup_named("reset")?;
+    let dir_a = test_dir.join("a");
+    let dir_b = test_dir.join("b");
+    std::fs::create_dir_all(&dir_a)?;
+    std::fs::create_dir_all(&dir_b)?;
+    common::create_file(&dir_a, "x", b"hello")?;
+    common::create_file(&dir_a, "y", b"hello")?;
+    common::create_file(&dir_b, "z", b"world")?;
+    common::create_file(&dir_b, "w", b"world")?;
+    common::create_file(&dir_b, "v", b"hello")?;
+
+    let mut jo = justone::JustOne::with_config(justone::StrictLevel::ByteByByte, false)
+        .with_progress_observer(justone::NoopObserver);
+    jo.update(&dir_a)?;
+    jo.reset();
+    assert!(jo.duplicates()?.is_empty());
+    assert_eq!(jo.stats(), justone::Stats::default());
+    jo.update(&dir_b)?;
+
+    let mut fresh = justone::JustOne::with_config(justone::StrictLevel::ByteByByte, false)
+        .with_progress_observer(justone::NoopObserver);
+    fresh.update(&dir_b)?;
+    assert_eq!(jo.duplicates_sorted()?, fresh.duplicates_sorted()?);
+    assert_eq!(jo.duplicates_sorted()?.len(), 1);
+    assert_eq!(jo.stats(), fresh.stats());
+
+    common::teardown_named("reset")?;
+    Ok(())
+}
+
+#[test]
+fn duplicates_with_hash_shows_hash_and_size() -> justone::Result<()> {
+    let test_dir = common::setup_named("with_hash")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", &[1; 1536])?;
+    common::create_file(&test_dir, "d", &[1; 1536])?;
+
+    let mut jo = justone::JustOne::new().with_algorithm(justone::HashAlgorithm::Sha256);
+    jo.update(&test_dir)?;
+    let mut groups = jo.duplicates_with_hash()?;
+    groups.sort_unstable_by_key(|group| group.size);
+    assert_eq!(
+        groups[0].hash,
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(
+        groups[0].to_string(),
+        "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824 (2 files, 5 B each)"
+    );
+    assert!(groups[1].to_string().ends_with("(2 files, 1.5 KiB each)"));
+
+    common::teardown_named("with_hash")?;
+    Ok(())
+}
+
+#[test]
+fn verify_group_detects_changed_file() -> justone::Result<()> {
+    let test_dir = common::setup_named("verify_group")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let group = jo.duplicates_sorted()?.remove(0);
+    assert_eq!(jo.verify_group(&group, false)?, None);
+
+    // same size, so only the content tells them apart
+    common::create_file(&test_dir, "c", b"jello")?;
+    assert_eq!(
+        jo.verify_group(&group, false)?,
+        Some((test_dir.join("a").as_path(), test_dir.join("c").as_path()))
+    );
+
+    common::teardown_named("verify_group")?;
+    Ok(())
+}
+
+#[test]
+fn case_insensitive_paths_index_file_once() -> justone::Result<()> {
+    let test_dir = common::setup_named("case_insensitive")?;
+    let sub_dir = test_dir.join("Data");
+    std::fs::create_dir_all(&sub_dir)?;
+    common::create_file(&sub_dir, "Photo.jpg", b"hello")?;
+    common::create_file(&test_dir, "copy.jpg", b"hello")?;
+
+    // on a case-insensitive filesystem both spellings reach the same file, on the others
+    // the second one only needs to exist for the scan
+    let lower_dir = test_dir.join("data");
+    if !lower_dir.exists() {
+        std::fs::create_dir_all(&lower_dir)?;
+        common::create_file(&lower_dir, "photo.jpg", b"hello")?;
+    }
+
+    let mut jo = justone::JustOne::new().with_case_insensitive_paths(true);
+    jo.update(&sub_dir)?.update(&lower_dir)?.update(&test_dir)?;
+    let dups = jo.duplicates_sorted()?;
+    assert_eq!(
+        dups,
+        vec![vec![test_dir.join("Data").join("Photo.jpg").as_path(), test_dir.join("copy.jpg").as_path()]]
+    );
+    assert!(jo.file_times(lower_dir.join("PHOTO.JPG")).is_some());
+
+    common::teardown_named("case_insensitive")?;
+    Ok(())
+}
+
+#[test]
+fn summary_counts_redundant_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("summary")?;
+    common::create_file(&test_dir, "a", &[1; 1024])?;
+    common::create_file(&test_dir, "b", &[1; 1024])?;
+    common::create_file(&test_dir, "c", &[1; 1024])?;
+    common::create_file(&test_dir, "d", b"hello")?;
+    common::create_file(&test_dir, "e", b"hello")?;
+    common::create_file(&test_dir, "f", b"world!")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let summary = jo.summary()?;
+    assert_eq!(
+        summary,
+        justone::Summary {
+            groups: 2,
+            redundant_files: 3,
+            reclaimable_bytes: 2 * 1024 + 5,
+        }
+    );
+    assert_eq!(summary.reclaimable_bytes, jo.wasted_bytes()?);
+    assert_eq!(
+        summary.to_string(),
+        "Found 2 duplicate groups, 3 redundant files, 2.0 KiB reclaimable."
+    );
+
+    common::teardown_named("summary")?;
+    Ok(())
+}
+
+#[test]
+fn uniques_include_files_told_apart_early() -> justone::Result<()> {
+    let test_dir = common::setup_named("uniques")?;
+    let other_dir = test_dir.join("other");
+    std::fs::create_dir_all(&other_dir)?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&other_dir, "b", b"hello")?;
+    // same size, different small hash
+    common::create_file(&test_dir, "c", b"world")?;
+    // unique size
+    common::create_file(&other_dir, "d", b"hello world")?;
+    // same small hash, different full hash
+    let mut tail = vec![1; 1024];
+    tail.extend_from_slice(&[2; 1024]);
+    common::create_file(&test_dir, "e", &[1; 2048])?;
+    common::create_file(&other_dir, "f", &tail)?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let mut uniques = jo.uniques()?;
+    uniques.sort_unstable();
+    assert_eq!(
+        uniques,
+        vec![test_dir.join("c").as_path(), test_dir.join("e").as_path(), other_dir.join("d").as_path(), other_dir.join("f").as_path()]
+    );
+    let dups = jo.duplicates()?;
+    assert!(dups.iter().flatten().all(|path| !uniques.contains(path)));
+
+    common::teardown_named("uniques")?;
+    Ok(())
+}
+
+#[test]
+fn merge_finds_duplicates_across_indexes() -> justone::Result<()> {
+    let test_dir = common::setup_named("merge")?;
+    let dir_a = test_dir.join("a");
+    let dir_b = test_dir.join("b");
+    std::fs::create_dir_all(&dir_a)?;
+    std::fs::create_dir_all(&dir_b)?;
+    // unique in size within each folder, so neither index hashed it
+    common::create_file(&dir_a, "x", b"across")?;
+    common::create_file(&dir_b, "y", b"across")?;
+    common::create_file(&dir_b, "z", b"within")?;
+    common::create_file(&dir_b, "w", b"within")?;
+    common::create_file(&dir_a, "v", b"alone")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&dir_a)?;
+    let mut other = justone::JustOne::new();
+    other.update(&dir_b)?;
+    jo.merge(other)?;
+    let mut sequential = justone::JustOne::new();
+    sequential.update(&dir_a)?.update(&dir_b)?;
+    assert_eq!(jo.duplicates_sorted()?, sequential.duplicates_sorted()?);
+    assert_eq!(
+        jo.duplicates_sorted()?,
+        vec![
+            vec![dir_a.join("x").as_path(), dir_b.join("y").as_path()],
+            vec![dir_b.join("w").as_path(), dir_b.join("z").as_path()],
+        ]
+    );
+    assert_eq!(jo.uniques()?, vec![dir_a.join("v").as_path()]);
+
+    let mut other = justone::JustOne::new().with_seed(42);
+    other.update(&dir_b)?;
+    assert!(matches!(
+        jo.merge(other),
+        Err(justone::JustOneError::IncompatibleIndex(_))
+    ));
+
+    common::teardown_named("merge")?;
+    Ok(())
+}
+
+#[test]
+fn io_rate_limit_throttles_hashing() -> justone::Result<()> {
+    let test_dir = common::setup_named("io_rate_limit")?;
+    common::create_file(&test_dir, "a", &[7; 32 * 1024])?;
+    common::create_file(&test_dir, "b", &[7; 32 * 1024])?;
+
+    // about 66 KiB are read, at 128 KiB/s
+    let start = std::time::Instant::now();
+    let mut jo = justone::JustOne::new().with_io_rate_limit(128 * 1024);
+    jo.update(&test_dir)?;
+    assert!(start.elapsed() >= std::time::Duration::from_millis(400));
+    assert_eq!(jo.duplicates()?.len(), 1);
+
+    common::teardown_named("io_rate_limit")?;
+    Ok(())
+}
+
+#[test]
+fn skip_small_hash_reads_small_files_once() -> justone::Result<()> {
+    let test_dir = common::setup_named("skip_small_hash")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    let mut a = vec![0u8; 4096];
+    let mut b = a.clone();
+    a[3000] = 1;
+    b[3000] = 2;
+    common::create_file(&test_dir, "c", &a)?;
+    common::create_file(&test_dir, "d", &b)?;
+
+    // the small files are read once for both hashes, the larger ones twice
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let stats = jo.stats();
+    assert_eq!((stats.small_hashes, stats.full_hashes), (4, 4));
+    assert_eq!(stats.bytes_read, 2 * 5 + 2 * (1024 + 4096));
+    let (path_a, path_b) = (test_dir.join("a"), test_dir.join("b"));
+    let expected = vec![vec![path_a.as_path(), path_b.as_path()]];
+    assert_eq!(jo.duplicates_sorted()?, expected);
+
+    let mut jo = justone::JustOne::new().with_skip_small_hash(true);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.stats().bytes_read, 2 * 5 + 2 * 4096);
+    assert_eq!(jo.duplicates_sorted()?, expected);
+
+    common::teardown_named("skip_small_hash")?;
+    Ok(())
+}
+
+#[test]
+fn len_and_contains_reflect_the_index() -> justone::Result<()> {
+    let test_dir = common::setup_named("len_contains")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"unique")?;
+
+    let mut jo = justone::JustOne::new();
+    assert!(jo.is_empty());
+    jo.update(&test_dir)?;
+    assert_eq!(jo.len(), 3);
+    assert_eq!(jo.file_count(), 3);
+    assert_eq!(jo.total_size(), 16);
+    assert!(!jo.is_empty());
+    assert!(jo.contains(test_dir.join("c")));
+    assert!(!jo.contains(test_dir.join("d")));
+
+    assert!(jo.remove_file(test_dir.join("c")));
+    assert_eq!(jo.len(), 2);
+    assert_eq!(jo.file_count(), 2);
+    assert_eq!(jo.total_size(), 10);
+    assert!(!jo.contains(test_dir.join("c")));
+
+    common::teardown_named("len_contains")?;
+    Ok(())
+}
+
+#[test]
+fn update_files_indexes_a_given_list() -> justone::Result<()> {
+    let test_dir = common::setup_named("update_files")?;
+    let a = common::create_file(&test_dir, "x/a", b"hello")?;
+    let b = common::create_file(&test_dir, "y/b", b"hello")?;
+    let c = common::create_file(&test_dir, "y/c", b"world")?;
+    // not in the list, so not indexed
+    common::create_file(&test_dir, "y/d", b"world")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update_files(vec![a.clone(), b.clone(), c.clone()])?;
+    assert_eq!(jo.len(), 3);
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![a.as_path(), b.as_path()]]);
+
+    let missing = test_dir.join("missing");
+    let mut jo = justone::JustOne::new();
+    assert!(jo.update_files(vec![a.clone(), missing.clone()]).is_err());
+
+    let mut jo = justone::JustOne::builder().ignore_error(true).build();
+    jo.update_files(vec![a.clone(), missing.clone(), test_dir.join("x"), b.clone()])?;
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![a.as_path(), b.as_path()]]);
+    let skipped: Vec<&std::path::Path> = jo.ignored().iter().map(|ignored| ignored.path.as_path()).collect();
+    assert_eq!(skipped, vec![missing.as_path(), test_dir.join("x").as_path()]);
+
+    common::teardown_named("update_files")?;
+    Ok(())
+}
+
+#[test]
+fn normalize_text_matches_crlf_and_lf_copies() -> justone::Result<()> {
+    let test_dir = common::setup_named("normalize_text")?;
+    let lf = common::create_file(&test_dir, "lf.txt", b"first line\nsecond line\n")?;
+    let crlf = common::create_file(&test_dir, "crlf.txt", b"first line  \r\nsecond line\r\n")?;
+    // binary files are compared as they are
+    common::create_file(&test_dir, "lf.bin", b"\0first\n")?;
+    common::create_file(&test_dir, "crlf.bin", b"\0first\r\n")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert!(jo.duplicates()?.is_empty());
+
+    let mut jo = justone::JustOne::new().with_normalize_text(true);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![crlf.as_path(), lf.as_path()]]);
+    assert_eq!(jo.find_duplicates_of(&lf)?, vec![crlf.as_path()]);
+
+    common::teardown_named("normalize_text")?;
+    Ok(())
+}
+
+#[test]
+fn root_of_tells_the_scanned_folder_of_each_duplicate() -> justone::Result<()> {
+    let test_dir = common::setup_named("root_of")?;
+    let source = test_dir.join("source");
+    let backup = test_dir.join("backup");
+    let a = common::create_file(&source, "a", b"hello")?;
+    let b = common::create_file(&backup, "2020/a", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update_all(&[&source, &backup])?;
+    assert_eq!(jo.roots(), &[source.clone(), backup.clone()]);
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![b.as_path(), a.as_path()]]);
+    assert_eq!(jo.root_of(&a), Some(source.as_path()));
+    assert_eq!(jo.root_of(&b), Some(backup.as_path()));
+    assert_eq!(jo.root_of(test_dir.join("missing")), None);
+
+    common::teardown_named("root_of")?;
+    Ok(())
+}
+
+#[test]
+fn update_file_roots_the_file_under_its_folder() -> justone::Result<()> {
+    let test_dir = common::setup_named("update_file")?;
+    let source = test_dir.join("source");
+    let backup = test_dir.join("backup");
+    let a = common::create_file(&source, "a", b"hello")?;
+    let b = common::create_file(&backup, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update_file(&a)?.update_file(&b)?;
+    assert_eq!(jo.roots(), &[source.clone(), backup.clone()]);
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![b.as_path(), a.as_path()]]);
+    assert_eq!(jo.root_of(&a), Some(source.as_path()));
+    assert_eq!(jo.root_of(&b), Some(backup.as_path()));
+
+    common::teardown_named("update_file")?;
+    Ok(())
+}
+
+#[test]
+fn min_size_skips_smaller_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("min_size")?;
+    common::create_file(&test_dir, "a", b"hi")?;
+    common::create_file(&test_dir, "b", b"hi")?;
+    let c = common::create_file(&test_dir, "c", b"hello")?;
+    let d = common::create_file(&test_dir, "d", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_min_size(5);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.len(), 2);
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![c.as_path(), d.as_path()]]);
+
+    common::teardown_named("min_size")?;
+    Ok(())
+}
+
+#[test]
+fn max_size_skips_and_lists_larger_files() -> justone::Result<()> {
+    let test_dir = common::setup_named("max_size")?;
+    let a = common::create_file(&test_dir, "a", b"hi")?;
+    let b = common::create_file(&test_dir, "b", b"hi")?;
+    let c = common::create_file(&test_dir, "c", b"hello")?;
+    let d = common::create_file(&test_dir, "d", b"hello")?;
+
+    let mut jo = justone::JustOne::new().with_max_size(4);
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![a.as_path(), b.as_path()]]);
+    let mut too_large = jo.too_large().to_vec();
+    too_large.sort_unstable();
+    assert_eq!(too_large, vec![c.clone(), d.clone()]);
+
+    // the files met again by later scans are listed once
+    jo.update(&test_dir)?.update_files(vec![c.clone()])?;
+    let mut too_large = jo.too_large().to_vec();
+    too_large.sort_unstable();
+    assert_eq!(too_large, vec![c, d]);
+
+    jo.reset();
+    assert!(jo.too_large().is_empty());
+
+    common::teardown_named("max_size")?;
+    Ok(())
+}
+
+#[test]
+fn group_and_duplicate_counts_match_duplicates() -> justone::Result<()> {
+    let test_dir = common::setup_named("counts")?;
+    common::create_file(&test_dir, "a", b"hello")?;
+    common::create_file(&test_dir, "b", b"hello")?;
+    common::create_file(&test_dir, "c", b"hello")?;
+    common::create_file(&test_dir, "d", b"world")?;
+    common::create_file(&test_dir, "e", b"world")?;
+    common::create_file(&test_dir, "f", b"unique")?;
+
+    for strict_level in [justone::StrictLevel::Common, justone::StrictLevel::ByteByByte] {
+        let mut jo = justone::JustOne::with_config(strict_level, false);
+        jo.update(&test_dir)?;
+        assert_eq!(jo.group_count()?, 2);
+        assert_eq!(jo.duplicate_count()?, 3);
+    }
+
+    common::teardown_named("counts")?;
+    Ok(())
+}
+
+#[test]
+fn duplicates_by_size_puts_the_largest_first() -> justone::Result<()> {
+    let test_dir = common::setup_named("by_size")?;
+    let a = common::create_file(&test_dir, "a", b"hi")?;
+    let b = common::create_file(&test_dir, "b", b"hi")?;
+    let c = common::create_file(&test_dir, "c", b"ho")?;
+    let d = common::create_file(&test_dir, "d", b"ho")?;
+    let e = common::create_file(&test_dir, "e", b"hello")?;
+    let f = common::create_file(&test_dir, "f", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(
+        jo.duplicates_by_size()?,
+        vec![
+            (5, vec![e.as_path(), f.as_path()]),
+            (2, vec![a.as_path(), b.as_path()]),
+            (2, vec![c.as_path(), d.as_path()]),
+        ]
+    );
+
+    common::teardown_named("by_size")?;
+    Ok(())
+}
+
+#[test]
+fn keep_first_alphabetical_picks_the_smallest_path() -> justone::Result<()> {
+    let test_dir = common::setup_named("keep_alphabetical")?;
+    let a = common::create_file(&test_dir, "a/c", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    let plan = jo.plan_removals(justone::KeepStrategy::FirstAlphabetical)?;
+    assert_eq!(
+        plan,
+        vec![justone::RemovalPlan {
+            kept: a,
+            remove: vec![b],
+        }]
+    );
+
+    common::teardown_named("keep_alphabetical")?;
+    Ok(())
+}
+
+#[test]
+fn update_rehashes_a_modified_file() -> justone::Result<()> {
+    let test_dir = common::setup_named("rehash_modified")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    let c = common::create_file(&test_dir, "c", b"world")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![a.as_path(), b.as_path()]]);
+
+    // same size, another content and modified time
+    let f = std::fs::OpenOptions::new().write(true).open(&b)?;
+    std::io::Write::write_all(&mut &f, b"world")?;
+    f.set_modified(std::time::SystemTime::UNIX_EPOCH)?;
+    drop(f);
+
+    jo.update(&test_dir)?;
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![b.as_path(), c.as_path()]]);
+    assert_eq!(jo.len(), 3);
+
+    common::teardown_named("rehash_modified")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_internal_links_skips_links_leaving_the_roots() -> justone::Result<()> {
+    let test_dir = common::setup_named("follow_internal_links")?;
+    let root = test_dir.join("root");
+    let outside = test_dir.join("outside");
+    common::create_file(&root, "data/x", b"hello")?;
+    common::create_file(&outside, "y", b"hello")?;
+    std::os::unix::fs::symlink(root.join("data"), root.join("in_link"))?;
+    std::os::unix::fs::symlink(&outside, root.join("out_link"))?;
+
+    let mut jo = justone::JustOne::builder().follow_links(true).build();
+    jo.update(&root)?;
+    assert!(jo.contains(root.join("out_link/y")));
+
+    let mut jo = justone::JustOne::builder().follow_internal_links(true).build();
+    jo.update(&root)?;
+    assert!(!jo.contains(root.join("out_link/y")));
+    assert_eq!(jo.len(), 1);
+
+    let mut jo =
+        justone::JustOne::new().with_symlink_policy(justone::SymlinkPolicy::FollowInternal);
+    jo.update(&root)?;
+    assert_eq!(jo.len(), 1);
+
+    // skipping the symlinks wins over following them, whatever the order
+    let mut jo = justone::JustOne::builder()
+        .ignore_symlink(true)
+        .follow_internal_links(true)
+        .follow_links(true)
+        .build();
+    jo.update(&root)?;
+    assert_eq!(jo.len(), 1);
+    assert!(jo.contains(root.join("data/x")));
+
+    common::teardown_named("follow_internal_links")?;
+    Ok(())
+}
+
+#[test]
+fn prune_singletons_drops_unique_sizes() -> justone::Result<()> {
+    let test_dir = common::setup_named("prune_singletons")?;
+    let a = common::create_file(&test_dir, "a", b"hello")?;
+    let b = common::create_file(&test_dir, "b", b"hello")?;
+    let c = common::create_file(&test_dir, "c", b"unique")?;
+
+    let mut jo = justone::JustOne::new();
+    jo.update(&test_dir)?;
+    assert_eq!(jo.prune_singletons(), 1);
+    assert_eq!(jo.len(), 2);
+    assert!(!jo.contains(&c));
+    assert_eq!(jo.duplicates_sorted()?, vec![vec![a.as_path(), b.as_path()]]);
+
+    jo.update(&test_dir)?;
+    assert!(jo.contains(&c));
+
+    common::teardown_named("prune_singletons")?;
+    Ok(())
+}